@@ -0,0 +1,58 @@
+use std::{fs, path::Path};
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use sr_rs::models::match_struct::Match;
+use sr_rs::search::replace::apply_matches;
+use sr_rs::search::search::find_matches_in_file;
+use sr_rs::search::{replace_matches, ReplacePlan, SearchOptions};
+
+/// Contents of a file with `lines` lines and four matches on each
+fn large_file(lines: usize) -> String {
+    "let m = match_struct::new(match_struct, match_struct); // match_struct\n".repeat(lines)
+}
+
+/// Every match of the benchmark pattern, replaced with a shorter text
+fn find(contents: &str, file_path: &Path) -> Vec<Match> {
+    let matcher = SearchOptions::default()
+        .build_matcher("match_struct")
+        .unwrap();
+    let mut matches = find_matches_in_file(contents, matcher.as_ref(), file_path);
+    for m in matches.iter_mut() {
+        m.set_replacement("matches".into());
+    }
+    matches
+}
+
+fn bench_apply_matches(c: &mut Criterion) {
+    let mut group = c.benchmark_group("apply_matches");
+    for lines in [1_000, 25_000] {
+        let contents = large_file(lines);
+        let matches = find(&contents, Path::new("bench.rs"));
+        group.bench_function(format!("{} matches", matches.len()), |b| {
+            b.iter_batched(
+                || (contents.clone(), matches.clone()),
+                |(contents, matches)| apply_matches(contents, matches),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_replace_matches(c: &mut Criterion) {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let file_path = temp_dir.path().join("bench.rs");
+    let contents = large_file(25_000);
+    let plan = ReplacePlan::new(find(&contents, &file_path));
+
+    c.bench_function("replace_matches 100000 matches", |b| {
+        b.iter_batched(
+            || fs::write(&file_path, &contents).unwrap(),
+            |_| replace_matches(&plan).unwrap(),
+            BatchSize::PerIteration,
+        )
+    });
+}
+
+criterion_group!(benches, bench_apply_matches, bench_replace_matches);
+criterion_main!(benches);
@@ -1,6 +1,25 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
 use tui_input::Input;
 
+use crate::file_group::{group_by_file, FileGroup, PreviewRow};
+use crate::history::{History, HistoryEntry, HistoryPicker};
+use crate::keymap::Keymap;
+use crate::session::{ExcludedMatch, Session};
+use crate::theme::Theme;
 use crate::{InputBox, InputMode};
+use sr_rs::backup::{is_orig_backup, Backups};
+use sr_rs::enums::backup_enums::BackupStyle;
+use sr_rs::enums::search_enums::{MatchSource, ReplaceTarget};
+use sr_rs::git::GitFilter;
+use sr_rs::models::match_struct::Match;
+use sr_rs::search::{
+    find_conflicts, find_renames, refactor, rename_paths, replace_matches, search, search_files,
+    search_rules, undo_renames, FileWatcher, Rename, RenamePlan, ReplacePlan, RuleSet,
+    SearchOptions, SearchQuery, SearchStats,
+};
 
 /// App holds the state of the application
 pub struct App {
@@ -10,6 +29,57 @@ pub struct App {
     pub input_mode: InputMode,
     /// Current input box selection
     pub input_box_selection: InputBox,
+    /// How the search pattern is matched
+    pub search_options: SearchOptions,
+    /// Matches found by the last search
+    pub matches: Vec<Match>,
+    /// Where the matches come from
+    pub match_source: MatchSource,
+    /// Rules searched instead of the input boxes when the source is a rules
+    /// file
+    pub rules: RuleSet,
+    /// Statistics about the last search
+    pub search_stats: SearchStats,
+    /// Index of the selected preview row in Refine mode
+    pub selected_row: usize,
+    /// Files whose matches are hidden in the preview
+    pub collapsed_files: HashSet<String>,
+    /// Result of the last action, shown in the status bar
+    pub status_message: Option<String>,
+    /// Watches the files of the Filepath glob in watch mode
+    pub watcher: Option<FileWatcher>,
+    /// Whether file contents or paths are searched and replaced
+    pub replace_target: ReplaceTarget,
+    /// Renames found by the last search of paths
+    pub renames: RenamePlan,
+    /// Renames done so far, latest last, for undoing them
+    pub rename_history: Vec<Vec<Rename>>,
+    /// Limits the files searched to the ones git knows about
+    pub git: GitFilter,
+    /// Whether files with uncommitted changes in git are refused unless the
+    /// replace is forced
+    pub require_committed: bool,
+    /// Where the originals of replaced files are saved, if anywhere
+    pub backups: Option<Backups>,
+    /// Lines shown above and below each match in the preview
+    pub context_lines: usize,
+    /// Lines of the files with matches, for showing context around them
+    pub file_lines: HashMap<String, Vec<String>>,
+    /// The actions of the keys in each input mode
+    pub keymap: Keymap,
+    /// The styles the TUI is drawn with
+    pub theme: Theme,
+    /// Previous searches, recalled in the input boxes and the picker
+    pub history: History,
+    /// Position of the value recalled in the selected input box, latest
+    /// first
+    pub history_position: Option<usize>,
+    /// Value of the selected input box before recalling older ones
+    pub history_draft: String,
+    /// The history popup of History mode
+    pub history_picker: HistoryPicker,
+    /// Where the session is saved
+    pub session_path: Option<PathBuf>,
 }
 
 impl Default for App {
@@ -18,6 +88,906 @@ impl Default for App {
             input: vec![Input::default(), Input::default(), Input::default()],
             input_mode: InputMode::Normal,
             input_box_selection: InputBox::Search,
+            search_options: SearchOptions::default(),
+            matches: vec![],
+            match_source: MatchSource::Search,
+            rules: RuleSet::default(),
+            search_stats: SearchStats::default(),
+            selected_row: 0,
+            collapsed_files: HashSet::new(),
+            status_message: None,
+            watcher: None,
+            replace_target: ReplaceTarget::Contents,
+            renames: RenamePlan::default(),
+            rename_history: vec![],
+            git: GitFilter::default(),
+            require_committed: false,
+            backups: None,
+            context_lines: 0,
+            file_lines: HashMap::new(),
+            keymap: Keymap::default(),
+            theme: Theme::default(),
+            history: History::default(),
+            history_position: None,
+            history_draft: String::new(),
+            history_picker: HistoryPicker::default(),
+            session_path: None,
+        }
+    }
+}
+
+impl App {
+    /// Replaces the value of an input box.
+    pub fn set_input(&mut self, input_box: InputBox, value: String) {
+        self.input[input_box.pos()] = Input::new(value);
+    }
+
+    /// Updates the matches after the value of the selected input box
+    /// changed.
+    pub fn input_changed(&mut self) {
+        match self.input_box_selection {
+            InputBox::Replace => self.update_replacement(),
+            InputBox::Search | InputBox::Filepath => {
+                // Editing the search replaces any imported matches
+                self.match_source = MatchSource::Search;
+                self.refresh_matches();
+            }
+        }
+    }
+
+    /// Adds the values of the input boxes to the history.
+    pub fn record_history(&mut self) {
+        self.history.record(HistoryEntry {
+            search: self.input[InputBox::Search.pos()].value().to_string(),
+            replace: self.input[InputBox::Replace.pos()].value().to_string(),
+            glob: self.input[InputBox::Filepath.pos()].value().to_string(),
+        });
+    }
+
+    /// Replaces the value of the selected input box with an older value from
+    /// the history, or a newer one, back to what was typed.
+    pub fn recall_history(&mut self, older: bool) {
+        let pos = self.input_box_selection.pos();
+        let values = self.history.values(&self.input_box_selection);
+        let position = match (self.history_position, older) {
+            (None, false) => return,
+            (None, true) if values.is_empty() => return,
+            (None, true) => {
+                self.history_draft = self.input[pos].value().to_string();
+                Some(0)
+            }
+            (Some(position), true) => Some((position + 1).min(values.len() - 1)),
+            (Some(0), false) => None,
+            (Some(position), false) => Some(position - 1),
+        };
+        let value = match position {
+            Some(position) => values[position].to_string(),
+            None => self.history_draft.clone(),
+        };
+        self.history_position = position;
+        if value != self.input[pos].value() {
+            self.input[pos] = Input::new(value);
+            self.input_changed();
+        }
+    }
+
+    /// Fills the input boxes with the entry selected in the history picker
+    /// and searches again.
+    pub fn run_from_history(&mut self) {
+        let Some(entry) = self.history_picker.selected_entry(&self.history).cloned() else {
+            return;
+        };
+        self.set_input(InputBox::Search, entry.search);
+        self.set_input(InputBox::Replace, entry.replace);
+        self.set_input(InputBox::Filepath, entry.glob);
+        self.match_source = MatchSource::Search;
+        self.refresh_matches();
+    }
+
+    /// The state to save for reopening the app later.
+    pub fn session(&self) -> Session {
+        let mut collapsed_files: Vec<String> = self.collapsed_files.iter().cloned().collect();
+        collapsed_files.sort_unstable();
+        Session {
+            search: self.input[InputBox::Search.pos()].value().to_string(),
+            replace: self.input[InputBox::Replace.pos()].value().to_string(),
+            glob: self.input[InputBox::Filepath.pos()].value().to_string(),
+            options: self.search_options,
+            replace_target: self.replace_target,
+            excluded: self
+                .matches
+                .iter()
+                .filter(|m| m.is_excluded())
+                .map(ExcludedMatch::of)
+                .collect(),
+            collapsed_files,
+            selected_row: self.selected_row,
+        }
+    }
+
+    /// Saves the session to its file, the default one unless sr was started
+    /// with `--session`.
+    pub fn save_session(&mut self) -> std::io::Result<PathBuf> {
+        let path = match &self.session_path {
+            Some(path) => path.clone(),
+            None => Session::default_path().ok_or_else(|| {
+                std::io::Error::other("no state directory, $XDG_STATE_HOME and $HOME are unset")
+            })?,
+        };
+        self.session().save(&path)?;
+        self.session_path = Some(path.clone());
+        Ok(path)
+    }
+
+    /// Searches again with the values of a saved session and excludes the
+    /// matches that were excluded then, if they are still in their files.
+    pub fn restore_session(&mut self, session: Session) {
+        self.set_input(InputBox::Search, session.search);
+        self.set_input(InputBox::Replace, session.replace);
+        self.set_input(InputBox::Filepath, session.glob);
+        self.search_options = session.options;
+        self.replace_target = session.replace_target;
+        self.match_source = MatchSource::Search;
+        self.refresh_matches();
+
+        let mut excluded: HashMap<ExcludedMatch, usize> = HashMap::new();
+        for m in session.excluded {
+            *excluded.entry(m).or_default() += 1;
+        }
+        for m in self.matches.iter_mut() {
+            if let Some(count) = excluded.get_mut(&ExcludedMatch::of(m)).filter(|c| **c > 0) {
+                m.set_excluded(true);
+                *count -= 1;
+            }
+        }
+        let missing: usize = excluded.values().sum();
+        if missing > 0 {
+            self.status_message = Some(format!(
+                "{} excluded matches are no longer in their files",
+                missing
+            ));
+        }
+
+        let filepaths: HashSet<&str> = self.matches.iter().map(|m| m.get_filepath()).collect();
+        self.collapsed_files = session
+            .collapsed_files
+            .into_iter()
+            .filter(|f| filepaths.contains(f.as_str()))
+            .collect();
+        self.selected_row = session.selected_row;
+        self.clamp_selection();
+    }
+
+    /// Re-runs the search using the current input box values. Imported
+    /// matches are kept, only their replacement is updated.
+    pub fn refresh_matches(&mut self) {
+        match self.match_source {
+            MatchSource::Search => {}
+            MatchSource::Ripgrep => {
+                self.update_replacement();
+                return;
+            }
+            MatchSource::Rules => {
+                self.refresh_rule_matches();
+                return;
+            }
+        }
+
+        // Follow the Filepath glob while watching
+        let search_glob = self.input[InputBox::Filepath.pos()].value();
+        if self
+            .watcher
+            .as_ref()
+            .is_some_and(|w| w.glob() != search_glob)
+        {
+            self.watch();
+        }
+
+        self.matches = vec![];
+        self.search_stats = SearchStats::default();
+        if self.replace_target.includes_contents() {
+            match search(&self.query()) {
+                Ok((matches, stats)) => {
+                    self.matches = matches;
+                    self.search_stats = stats;
+                }
+                Err(e) => self.status_message = Some(format!("Invalid pattern: {}", e)),
+            }
+        }
+        self.renames = RenamePlan::default();
+        self.update_replacement();
+        self.clamp_selection();
+        self.load_context();
+    }
+
+    /// Applies the Search and Replace boxes to the paths matching the
+    /// Filepath glob.
+    fn refresh_renames(&mut self) {
+        let replacement = self.input[InputBox::Replace.pos()].value();
+        match find_renames(&self.query(), replacement) {
+            Ok(renames) => self.renames = RenamePlan::new(renames),
+            Err(e) => {
+                self.renames = RenamePlan::default();
+                self.status_message = Some(format!("Invalid pattern: {}", e));
+            }
+        }
+    }
+
+    /// The query of the Search and Filepath boxes.
+    fn query(&self) -> SearchQuery {
+        let search_pattern = self.input[InputBox::Search.pos()].value().to_string();
+        let search_glob = self.input[InputBox::Filepath.pos()].value().to_string();
+        SearchQuery::new(search_pattern)
+            .glob(search_glob)
+            .options(self.search_options)
+            .git(self.git.clone())
+            .skip_orig_backups(self.writes_orig_backups())
+    }
+
+    /// Whether replacing leaves `.orig` backups next to the files.
+    fn writes_orig_backups(&self) -> bool {
+        self.backups
+            .as_ref()
+            .is_some_and(|b| b.get_style() == BackupStyle::Orig)
+    }
+
+    /// Starts watching the files of the Filepath glob, so the matches follow
+    /// changes made to them on disk.
+    pub fn watch(&mut self) {
+        let search_glob = match self.match_source {
+            MatchSource::Search => self.input[InputBox::Filepath.pos()].value().to_string(),
+            // Watch everything below the working directory for other sources
+            MatchSource::Ripgrep | MatchSource::Rules => "**/*".to_string(),
+        };
+        match FileWatcher::new(&search_glob) {
+            Ok(watcher) => self.watcher = Some(watcher),
+            Err(e) => {
+                log::error!("Failed to watch {}: {}", search_glob, e);
+                self.watcher = None;
+                self.status_message = Some(format!("Failed to watch files: {}", e));
+            }
+        }
+    }
+
+    /// Updates the matches of the files that changed on disk since the last
+    /// call, when watching.
+    pub fn poll_file_changes(&mut self) {
+        let changed = match &self.watcher {
+            Some(watcher) => watcher.changed_paths(),
+            None => return,
+        };
+        if changed.is_empty() {
+            return;
+        }
+        log::info!("Files changed on disk: {:?}", changed);
+
+        match self.match_source {
+            MatchSource::Search => self.research_files(&changed),
+            MatchSource::Ripgrep => self.mark_stale(&changed),
+            MatchSource::Rules => self.refresh_rule_matches(),
+        }
+        self.status_message = Some(format!("{} files changed on disk", changed.len()));
+    }
+
+    /// Marks the matches of the changed files as stale. Imported matches
+    /// can't be searched again, their offsets may be wrong now.
+    fn mark_stale(&mut self, changed_paths: &[PathBuf]) {
+        // ripgrep's paths may be written differently, e.g. `./src/x.rs`
+        let changed: HashSet<PathBuf> = changed_paths.iter().map(|p| canonical(p)).collect();
+        for m in self.matches.iter_mut() {
+            if changed.contains(&canonical(Path::new(m.get_filepath()))) {
+                m.set_stale(true);
+            }
+        }
+    }
+
+    /// Searches the changed files again, swapping their matches in place.
+    /// Matches that are still found keep their exclusion.
+    fn research_files(&mut self, changed_paths: &[PathBuf]) {
+        // Files leaving the git filter lose their matches
+        let searched = match self.git.apply(changed_paths.to_vec()) {
+            Ok(searched) => searched,
+            Err(e) => {
+                log::error!("Failed to filter files with git: {}", e);
+                return;
+            }
+        };
+        let (mut found, _) = match search_files(&self.query(), &searched) {
+            Ok(result) => result,
+            Err(_) => return,
+        };
+        let replacement = self.input[InputBox::Replace.pos()].value().to_string();
+        for m in found.iter_mut() {
+            m.set_replacement_template(&replacement);
+        }
+
+        let changed: HashSet<String> = changed_paths
+            .iter()
+            .map(|path| path.to_string_lossy().into_owned())
+            .collect();
+        // Matches on identical lines are told apart by their order
+        let mut excluded: HashMap<(String, String, usize), Vec<bool>> = HashMap::new();
+        for m in self.matches.iter() {
+            if changed.contains(m.get_filepath()) {
+                excluded
+                    .entry(exclusion_key(m))
+                    .or_default()
+                    .push(m.is_excluded());
+            }
+        }
+        let mut seen: HashMap<(String, String, usize), usize> = HashMap::new();
+        for m in found.iter_mut() {
+            let key = exclusion_key(m);
+            let nth = seen.entry(key.clone()).or_default();
+            let was_excluded = excluded.get(&key).and_then(|e| e.get(*nth));
+            m.set_excluded(was_excluded.copied().unwrap_or(false));
+            *nth += 1;
+        }
+
+        // Keep the files in the order they were first found
+        let mut found_by_file: HashMap<String, Vec<Match>> = HashMap::new();
+        for m in found {
+            found_by_file
+                .entry(m.get_filepath().to_string())
+                .or_default()
+                .push(m);
+        }
+        let mut matches = vec![];
+        for m in self.matches.drain(..) {
+            if !changed.contains(m.get_filepath()) {
+                matches.push(m);
+            } else if let Some(mut file_matches) = found_by_file.remove(m.get_filepath()) {
+                matches.append(&mut file_matches);
+            }
+        }
+        // Files that had no matches before go last
+        for path in changed_paths {
+            if let Some(mut file_matches) = found_by_file.remove(path.to_string_lossy().as_ref()) {
+                matches.append(&mut file_matches);
+            }
+        }
+        self.matches = matches;
+        self.search_stats.files_matched = group_by_file(&self.matches).len();
+        self.clamp_selection();
+        self.load_context();
+    }
+
+    /// Searches every rule of the rules file. Matches overlapping those of an
+    /// earlier rule start out excluded.
+    fn refresh_rule_matches(&mut self) {
+        match search_rules(&self.rules) {
+            Ok((mut matches, stats, overlaps)) => {
+                if self.writes_orig_backups() {
+                    matches.retain(|m| !is_orig_backup(Path::new(m.get_filepath())));
+                }
+                for overlap in &overlaps {
+                    log::warn!("{}", overlap);
+                }
+                if !overlaps.is_empty() {
+                    self.status_message = Some(format!(
+                        "{} overlapping rule matches excluded",
+                        overlaps.len()
+                    ));
+                }
+                self.matches = matches;
+                self.search_stats = stats;
+            }
+            Err(e) => {
+                self.matches = vec![];
+                self.search_stats = SearchStats::default();
+                self.status_message = Some(format!("Invalid rule pattern: {}", e));
+            }
+        }
+        self.clamp_selection();
+        self.load_context();
+    }
+
+    /// Uses the rules of a rules file instead of the input boxes.
+    pub fn load_rules(&mut self, rules: RuleSet) {
+        self.rules = rules;
+        self.match_source = MatchSource::Rules;
+        self.refresh_matches();
+    }
+
+    /// The replacements that would be written, leaving out excluded matches
+    /// and matches overlapping another one.
+    pub fn replace_plan(&self) -> ReplacePlan {
+        let plan = ReplacePlan::new(self.matches.iter().cloned());
+        match &self.backups {
+            Some(backups) => plan.backup(backups.clone()),
+            None => plan,
+        }
+    }
+
+    /// Indices of the matches that overlap another match and won't be
+    /// replaced.
+    pub fn conflicting_matches(&self) -> HashSet<usize> {
+        find_conflicts(&self.matches)
+            .into_iter()
+            .map(|(_, dropped)| dropped)
+            .collect()
+    }
+
+    /// Replaces every match that hasn't been excluded in Refine mode,
+    /// returning the number of matches skipped because they overlap another.
+    /// When searching paths, does the renames too. Forcing it replaces in
+    /// files with uncommitted changes as well.
+    pub fn replace(&mut self, force: bool) -> std::io::Result<usize> {
+        let plan = self
+            .replace_plan()
+            .require_committed(self.require_committed && !force);
+        for conflict in plan.conflicts() {
+            log::warn!("{}", conflict);
+        }
+        let done = match self.replace_target {
+            ReplaceTarget::Contents => {
+                replace_matches(&plan)?;
+                vec![]
+            }
+            ReplaceTarget::Paths => rename_paths(&self.renames)?,
+            ReplaceTarget::Both => refactor(&plan, &self.renames)?,
+        };
+        if !done.is_empty() {
+            self.rename_history.push(done);
+        }
+        Ok(plan.conflicts().len())
+    }
+
+    /// Reverts the last renames, returning how many paths were moved back.
+    pub fn undo_renames(&mut self) -> std::io::Result<usize> {
+        let Some(done) = self.rename_history.pop() else {
+            return Ok(0);
+        };
+        if let Err(e) = undo_renames(&done) {
+            // Keep them around to try again
+            self.rename_history.push(done);
+            return Err(e);
+        }
+        Ok(done.len())
+    }
+
+    /// Uses matches found by ripgrep instead of searching.
+    pub fn import_matches(&mut self, matches: Vec<Match>) {
+        self.search_stats = SearchStats {
+            files_matched: group_by_file(&matches).len(),
+            ..SearchStats::default()
+        };
+        self.matches = matches;
+        self.match_source = MatchSource::Ripgrep;
+        self.update_replacement();
+        self.clamp_selection();
+        self.load_context();
+    }
+
+    /// Applies the current value of the Replace box to every match, keeping
+    /// exclusions intact. Rule matches keep the replacement of their rule.
+    pub fn update_replacement(&mut self) {
+        if self.match_source == MatchSource::Rules {
+            return;
+        }
+        if self.replace_target.includes_paths() {
+            self.refresh_renames();
+        }
+        let replacement = self.input[InputBox::Replace.pos()].value().to_string();
+        for m in self.matches.iter_mut() {
+            m.set_replacement_template(&replacement);
+        }
+    }
+
+    /// Number of matches excluded from the replace list in Refine mode.
+    pub fn excluded_count(&self) -> usize {
+        self.matches.iter().filter(|m| m.is_excluded()).count()
+    }
+
+    /// Groups the current matches by file.
+    pub fn file_groups(&self) -> Vec<FileGroup> {
+        group_by_file(&self.matches)
+    }
+
+    /// Rows currently visible in the preview, skipping the matches of
+    /// collapsed files.
+    pub fn preview_rows(&self) -> Vec<PreviewRow> {
+        let mut rows = vec![];
+        for (group_index, group) in self.file_groups().iter().enumerate() {
+            rows.push(PreviewRow::File(group_index));
+            if !self.collapsed_files.contains(&group.filepath) {
+                rows.extend(group.match_indices.iter().map(|&i| PreviewRow::Match(i)));
+            }
+        }
+        rows
+    }
+
+    pub fn select_next_row(&mut self) {
+        if self.selected_row + 1 < self.preview_rows().len() {
+            self.selected_row += 1;
+        }
+    }
+
+    pub fn select_prev_row(&mut self) {
+        self.selected_row = self.selected_row.saturating_sub(1);
+    }
+
+    /// Excludes the selected match from the replace list, or includes it
+    /// again if it was already excluded. When a file header is selected the
+    /// whole file is excluded, or included if all its matches were excluded.
+    pub fn toggle_selected_excluded(&mut self) {
+        match self.preview_rows().get(self.selected_row) {
+            Some(PreviewRow::Match(i)) => {
+                let m = &mut self.matches[*i];
+                m.set_excluded(!m.is_excluded());
+            }
+            Some(PreviewRow::File(group_index)) => {
+                let group = &self.file_groups()[*group_index];
+                let exclude = group
+                    .match_indices
+                    .iter()
+                    .any(|&i| !self.matches[i].is_excluded());
+                for &i in &group.match_indices {
+                    self.matches[i].set_excluded(exclude);
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Collapses the file of the selected row, or expands it if it was
+    /// already collapsed. The selection moves to the file's header.
+    pub fn toggle_selected_collapsed(&mut self) {
+        let rows = self.preview_rows();
+        let group_index = match rows.get(self.selected_row) {
+            Some(PreviewRow::File(group_index)) => *group_index,
+            Some(PreviewRow::Match(i)) => {
+                let filepath = self.matches[*i].get_filepath();
+                match self
+                    .file_groups()
+                    .iter()
+                    .position(|g| g.filepath == filepath)
+                {
+                    Some(group_index) => group_index,
+                    None => return,
+                }
+            }
+            None => return,
+        };
+
+        let filepath = self.file_groups()[group_index].filepath.clone();
+        if !self.collapsed_files.remove(&filepath) {
+            self.collapsed_files.insert(filepath);
+        }
+        self.selected_row = self
+            .preview_rows()
+            .iter()
+            .position(|row| row == &PreviewRow::File(group_index))
+            .unwrap_or(0);
+    }
+
+    /// The match of the selected row, or the first match of the file when a
+    /// file header is selected.
+    pub fn selected_match(&self) -> Option<&Match> {
+        match self.preview_rows().get(self.selected_row)? {
+            PreviewRow::Match(i) => self.matches.get(*i),
+            PreviewRow::File(group_index) => {
+                let group = &self.file_groups()[*group_index];
+                self.matches.get(*group.match_indices.first()?)
+            }
+        }
+    }
+
+    /// The lines around a match within the context, above and below it.
+    pub fn context_of(&self, m: &Match) -> (&[String], &[String]) {
+        let Some(lines) = self.file_lines.get(m.get_filepath()) else {
+            return (&[], &[]);
+        };
+        let line_num = m.get_line_num().min(lines.len());
+        let before = line_num.saturating_sub(self.context_lines);
+        let after = (line_num + 1 + self.context_lines).min(lines.len());
+        (
+            &lines[before..line_num],
+            &lines[(line_num + 1).min(after)..after],
+        )
+    }
+
+    /// Reads the lines of the files with matches when showing context.
+    fn load_context(&mut self) {
+        self.file_lines.clear();
+        if self.context_lines == 0 {
+            return;
+        }
+        for group in self.file_groups() {
+            match std::fs::read_to_string(&group.filepath) {
+                Ok(contents) => {
+                    let lines = contents.lines().map(String::from).collect();
+                    self.file_lines.insert(group.filepath, lines);
+                }
+                Err(e) => log::error!("Failed to read context of {}: {}", group.filepath, e),
+            }
+        }
+    }
+
+    fn clamp_selection(&mut self) {
+        self.selected_row = self
+            .selected_row
+            .min(self.preview_rows().len().saturating_sub(1));
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Identifies a match across searches, even if lines were added above it.
+/// Matches on identical lines share a key.
+fn exclusion_key(m: &Match) -> (String, String, usize) {
+    (
+        m.get_filepath().to_string(),
+        m.get_line().to_string(),
+        m.get_start_on_line(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_replace_skips_excluded_matches() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_file_path = temp_dir.path().join("test_file.txt");
+        fs::write(&test_file_path, "Hello, world! Hello, world!\n")?;
+
+        let mut app = App::default();
+        app.set_input(InputBox::Search, "world".into());
+        app.set_input(InputBox::Replace, "Rust".into());
+        app.set_input(InputBox::Filepath, test_file_path.to_str().unwrap().into());
+        app.refresh_matches();
+        // Skip over the file header to the first match
+        app.select_next_row();
+        app.toggle_selected_excluded();
+
+        app.replace(false)?;
+
+        let content = fs::read_to_string(&test_file_path)?;
+        assert_eq!(content, "Hello, world! Hello, Rust!\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_toggle_file_exclusion() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        fs::write(temp_dir.path().join("a.txt"), "world world")?;
+        fs::write(temp_dir.path().join("b.txt"), "world")?;
+
+        let mut app = App::default();
+        app.set_input(InputBox::Search, "world".into());
+        app.set_input(
+            InputBox::Filepath,
+            format!("{}/*.txt", temp_dir.path().to_str().unwrap()),
+        );
+        app.refresh_matches();
+
+        // The first row is the header of a.txt
+        app.toggle_selected_excluded();
+        assert_eq!(app.excluded_count(), 2);
+        app.toggle_selected_excluded();
+        assert_eq!(app.excluded_count(), 0);
+
+        app.toggle_selected_collapsed();
+        assert_eq!(app.preview_rows().len(), 3);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_research_changed_files() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let a_path = temp_dir.path().join("a.txt");
+        let b_path = temp_dir.path().join("b.txt");
+        fs::write(&a_path, "world\nworld\n")?;
+        fs::write(&b_path, "world\n")?;
+
+        let mut app = App::default();
+        app.set_input(InputBox::Search, "world".into());
+        app.set_input(InputBox::Replace, "Rust".into());
+        app.set_input(
+            InputBox::Filepath,
+            format!("{}/*.txt", temp_dir.path().to_str().unwrap()),
+        );
+        app.refresh_matches();
+        app.matches[1].set_excluded(true);
+
+        // A line is added above the excluded match and b.txt is emptied
+        fs::write(&a_path, "new\nworld\nworld\n")?;
+        fs::write(&b_path, "")?;
+        app.research_files(&[a_path.clone(), b_path]);
+
+        assert_eq!(app.matches.len(), 2);
+        assert_eq!(app.matches[0].get_line_num(), 1);
+        assert!(!app.matches[0].is_excluded());
+        assert!(app.matches[1].is_excluded());
+        assert_eq!(app.matches[1].get_replacement(), "Rust");
+        assert_eq!(app.search_stats.files_matched, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_and_undo() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        fs::write(temp_dir.path().join("match_struct.rs"), "")?;
+
+        let mut app = App {
+            replace_target: ReplaceTarget::Paths,
+            ..App::default()
+        };
+        app.set_input(InputBox::Search, "match_struct".into());
+        app.set_input(InputBox::Replace, "matches".into());
+        app.set_input(
+            InputBox::Filepath,
+            format!("{}/*.rs", temp_dir.path().to_str().unwrap()),
+        );
+        app.refresh_matches();
+        assert!(app.matches.is_empty());
+        assert_eq!(app.renames.renames().len(), 1);
+
+        app.replace(false)?;
+        assert!(temp_dir.path().join("matches.rs").exists());
+        assert_eq!(app.undo_renames()?, 1);
+        assert!(temp_dir.path().join("match_struct.rs").exists());
+        assert_eq!(app.undo_renames()?, 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_skips_conflicting_matches() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_file_path = temp_dir.path().join("test_file.txt");
+        fs::write(&test_file_path, "abcdef\n")?;
+
+        let mut app = App::default();
+        app.set_input(InputBox::Replace, "X".into());
+        let mut matches = vec![];
+        for (start, length) in [(0, 4), (2, 3)] {
+            matches.push(
+                Match::new(
+                    test_file_path.to_str().unwrap().into(),
+                    start,
+                    length,
+                    start,
+                    start + length,
+                    "".into(),
+                    "abcdef".into(),
+                    0,
+                )
+                .with_text("abcdef"[start..start + length].into()),
+            );
         }
+        app.import_matches(matches);
+        assert_eq!(app.conflicting_matches(), HashSet::from([1]));
+
+        // Excluding the kept match resolves the conflict
+        app.matches[0].set_excluded(true);
+        assert!(app.conflicting_matches().is_empty());
+        app.matches[0].set_excluded(false);
+
+        assert_eq!(app.replace(false)?, 1);
+        assert_eq!(fs::read_to_string(&test_file_path)?, "Xef\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_context_lines() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_file_path = temp_dir.path().join("test_file.txt");
+        fs::write(&test_file_path, "one\ntwo\nworld\nthree\n")?;
+
+        let mut app = App {
+            context_lines: 2,
+            ..App::default()
+        };
+        app.set_input(InputBox::Search, "world".into());
+        app.set_input(InputBox::Filepath, test_file_path.to_str().unwrap().into());
+        app.refresh_matches();
+
+        let (before, after) = app.context_of(&app.matches[0]);
+        assert_eq!(before, ["one", "two"]);
+        assert_eq!(after, ["three"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_recall_history() {
+        let mut app = App::default();
+        for search in ["first", "second"] {
+            app.set_input(InputBox::Search, search.into());
+            app.record_history();
+        }
+        app.set_input(InputBox::Search, "draft".into());
+
+        let search = |app: &App| app.input[InputBox::Search.pos()].value().to_string();
+        app.recall_history(true);
+        assert_eq!(search(&app), "second");
+        app.recall_history(true);
+        app.recall_history(true);
+        assert_eq!(search(&app), "first");
+        app.recall_history(false);
+        app.recall_history(false);
+        assert_eq!(search(&app), "draft");
+        app.recall_history(false);
+        assert_eq!(search(&app), "draft");
+    }
+
+    #[test]
+    fn test_restore_session() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_file_path = temp_dir.path().join("test_file.txt");
+        fs::write(&test_file_path, "foo\nfoo bar\nfoo baz\n")?;
+
+        let mut app = App::default();
+        app.set_input(InputBox::Search, "foo".into());
+        app.set_input(InputBox::Filepath, test_file_path.to_str().unwrap().into());
+        app.refresh_matches();
+        app.matches[1].set_excluded(true);
+        app.matches[2].set_excluded(true);
+        app.selected_row = 2;
+        let session = app.session();
+
+        // Lines were added above one excluded match and the other was edited
+        fs::write(&test_file_path, "new\nfoo\nfoo bar\nfoo qux\n")?;
+        let mut app = App::default();
+        app.restore_session(session);
+
+        let excluded: Vec<bool> = app.matches.iter().map(|m| m.is_excluded()).collect();
+        assert_eq!(excluded, [false, true, false]);
+        assert_eq!(app.selected_row, 2);
+        assert!(app.status_message.is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn test_mark_stale_with_differently_written_paths() {
+        let mut app = App::default();
+        app.import_matches(vec![
+            Match::new("./Cargo.toml".into(), 0, 1, 0, 1, "".into(), "[".into(), 0),
+            Match::new("./src/lib.rs".into(), 0, 1, 0, 1, "".into(), "/".into(), 0),
+        ]);
+        app.mark_stale(&[fs::canonicalize("Cargo.toml").unwrap()]);
+
+        let stale: Vec<bool> = app.matches.iter().map(|m| m.is_stale()).collect();
+        assert_eq!(stale, [true, false]);
+    }
+
+    #[test]
+    fn test_orig_backups_are_not_searched() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        fs::write(temp_dir.path().join("a.txt"), "Hello, world!")?;
+
+        let mut app = App {
+            backups: Some(Backups::new(BackupStyle::Orig)),
+            ..App::default()
+        };
+        app.set_input(InputBox::Search, "Hello".into());
+        app.set_input(InputBox::Replace, "Bye".into());
+        app.set_input(
+            InputBox::Filepath,
+            format!("{}/*", temp_dir.path().to_str().unwrap()),
+        );
+        app.refresh_matches();
+        app.replace(false)?;
+        app.set_input(InputBox::Search, "world".into());
+        app.refresh_matches();
+
+        assert_eq!(app.matches.len(), 1);
+        app.replace(false)?;
+        assert!(!temp_dir.path().join("a.txt.orig.orig").exists());
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("a.txt.orig"))?,
+            "Bye, world!"
+        );
+        Ok(())
     }
 }
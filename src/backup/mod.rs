@@ -0,0 +1,352 @@
+use std::{
+    fs, io,
+    path::{Component, Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::dirs::state_dir;
+use crate::enums::backup_enums::BackupStyle;
+
+/// Number of backup directories kept by default, older ones are removed.
+pub const DEFAULT_KEEP: usize = 10;
+
+/// Prefix of the backup directories' names, followed by a timestamp.
+const DIR_PREFIX: &str = "sr-";
+
+/// File marking a directory as a backup made by sr. Only marked directories
+/// are listed, so pruning never removes anything else in the root.
+const MARKER: &str = ".sr-backup";
+
+/// Where and how the originals of replaced files are saved.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Backups {
+    style: BackupStyle,
+    root: PathBuf,
+    keep: usize,
+}
+
+impl Backups {
+    /// Backups of the style, in the default directory, keeping the
+    /// [`DEFAULT_KEEP`] most recent ones.
+    pub fn new(style: BackupStyle) -> Self {
+        Self {
+            style,
+            root: default_root(),
+            keep: DEFAULT_KEEP,
+        }
+    }
+
+    /// Sets the directory the timestamped backup directories are created in.
+    pub fn root<P: Into<PathBuf>>(mut self, root: P) -> Self {
+        self.root = root.into();
+        self
+    }
+
+    /// Sets how many backup directories are kept, the oldest ones being
+    /// removed after each replace. At least the latest one is always kept.
+    pub fn keep(mut self, keep: usize) -> Self {
+        self.keep = keep.max(1);
+        self
+    }
+
+    pub fn get_style(&self) -> BackupStyle {
+        self.style
+    }
+    pub fn get_root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Starts the backup of a single replace. Nothing is written until a file
+    /// is saved in it.
+    pub fn start(&self) -> Backup {
+        let dir = match self.style {
+            BackupStyle::Orig => None,
+            BackupStyle::Dir => {
+                let mut id = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |d| d.as_millis());
+                let name = |id: u128| format!("{}{}", DIR_PREFIX, id);
+                while self.root.join(name(id)).exists() {
+                    id += 1;
+                }
+                Some(self.root.join(name(id)))
+            }
+        };
+        Backup { dir }
+    }
+
+    /// The backup directories made by sr, oldest first.
+    pub fn list(&self) -> io::Result<Vec<PathBuf>> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e),
+        };
+        let mut dirs: Vec<(u128, PathBuf)> = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            let id = path.file_name().and_then(|name| backup_id(name.to_str()?));
+            if let Some(id) = id.filter(|_| path.join(MARKER).is_file()) {
+                dirs.push((id, path));
+            }
+        }
+        dirs.sort_unstable();
+        Ok(dirs.into_iter().map(|(_, path)| path).collect())
+    }
+
+    /// The backup directory with this name, which must be one made by sr
+    /// directly in the root.
+    fn find(&self, name: &str) -> io::Result<PathBuf> {
+        let dir = self.root.join(name);
+        if backup_id(name).is_none() || !dir.join(MARKER).is_file() {
+            return Err(io::Error::other(format!(
+                "no backup named {} in {}",
+                name,
+                self.root.display()
+            )));
+        }
+        Ok(dir)
+    }
+
+    /// Removes the oldest backup directories beyond the number kept.
+    pub fn prune(&self) -> io::Result<()> {
+        if self.style != BackupStyle::Dir {
+            return Ok(());
+        }
+        let dirs = self.list()?;
+        for dir in &dirs[..dirs.len().saturating_sub(self.keep)] {
+            fs::remove_dir_all(dir)?;
+        }
+        Ok(())
+    }
+
+    /// Puts the backed up files back in place, overwriting the replaced
+    /// ones. Backup directories are picked by name, the latest one by
+    /// default. `.orig` backups are restored for the given files instead.
+    pub fn restore(&self, id: Option<&str>, filepaths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+        match self.style {
+            BackupStyle::Orig => restore_orig(filepaths),
+            BackupStyle::Dir => {
+                let dir = match id {
+                    Some(id) => self.find(id)?,
+                    None => self.list()?.pop().ok_or_else(|| {
+                        io::Error::other(format!("no backups in {}", self.root.display()))
+                    })?,
+                };
+                restore_dir(&dir)
+            }
+        }
+    }
+}
+
+/// The backup of a single replace.
+#[derive(Debug)]
+pub struct Backup {
+    /// The timestamped directory, if backing up to one
+    dir: Option<PathBuf>,
+}
+
+impl Backup {
+    /// Copies the file to the backup, returning where it was saved.
+    pub fn save(&self, filepath: &Path) -> io::Result<PathBuf> {
+        let backup_path = match &self.dir {
+            Some(dir) => mirrored_path(dir, &fs::canonicalize(filepath)?),
+            None => orig_path(filepath),
+        };
+        if let Some(parent) = backup_path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        if let Some(dir) = &self.dir {
+            if !dir.join(MARKER).exists() {
+                fs::write(dir.join(MARKER), "")?;
+            }
+        }
+        fs::copy(filepath, &backup_path)?;
+        Ok(backup_path)
+    }
+
+    /// The directory the files are saved in, unless saved next to them.
+    pub fn dir(&self) -> Option<&Path> {
+        self.dir.as_deref()
+    }
+}
+
+/// The timestamp in the name of a backup directory, if it is one.
+fn backup_id(name: &str) -> Option<u128> {
+    name.strip_prefix(DIR_PREFIX)?.parse().ok()
+}
+
+/// Copies every file of a backup directory back to where it came from.
+fn restore_dir(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut restored = vec![];
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(current) = pending.pop() {
+        for entry in fs::read_dir(&current)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            if path == dir.join(MARKER) {
+                continue;
+            }
+            let relative = path.strip_prefix(dir).map_err(io::Error::other)?;
+            let original = Path::new(&Component::RootDir).join(relative);
+            fs::copy(&path, &original)?;
+            restored.push(original);
+        }
+    }
+    restored.sort();
+    Ok(restored)
+}
+
+/// Moves the `.orig` backups of the files that have one back in place.
+fn restore_orig(filepaths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut restored = vec![];
+    for filepath in filepaths {
+        let backup_path = orig_path(filepath);
+        if backup_path.is_file() {
+            fs::rename(&backup_path, filepath)?;
+            restored.push(filepath.clone());
+        }
+    }
+    Ok(restored)
+}
+
+/// Whether the file is a `.orig` backup, which shouldn't be searched along
+/// with the files it was made from.
+pub fn is_orig_backup(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "orig")
+}
+
+/// Where an absolute path is saved in a backup directory: the same path,
+/// below the directory instead of the root.
+fn mirrored_path(dir: &Path, path: &Path) -> PathBuf {
+    let mut mirrored = dir.to_path_buf();
+    for component in path.components() {
+        if let Component::Normal(name) = component {
+            mirrored.push(name);
+        }
+    }
+    mirrored
+}
+
+fn orig_path(filepath: &Path) -> PathBuf {
+    let mut name = filepath.file_name().unwrap_or_default().to_os_string();
+    name.push(".orig");
+    filepath.with_file_name(name)
+}
+
+/// `$XDG_STATE_HOME/sr/backups`, falling back to `~/.local/state`, so backups
+/// are never searched along with the files they were made from.
+fn default_root() -> PathBuf {
+    match state_dir() {
+        Some(state_dir) => state_dir.join("backups"),
+        None => PathBuf::from(".sr-backups"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_orig_backup_and_restore() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let filepath = dir.path().join("a.txt");
+        fs::write(&filepath, "Hello, world!")?;
+
+        let backups = Backups::new(BackupStyle::Orig);
+        let saved = backups.start().save(&filepath)?;
+        assert_eq!(saved, dir.path().join("a.txt.orig"));
+        fs::write(&filepath, "Hello, Rust!")?;
+
+        let restored = backups.restore(None, &[filepath.clone(), dir.path().join("b.txt")])?;
+        assert_eq!(restored, vec![filepath.clone()]);
+        assert_eq!(fs::read_to_string(&filepath)?, "Hello, world!");
+        assert!(!saved.exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_dir_backup_and_restore() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let filepath = dir.path().join("src/a.txt");
+        fs::create_dir(dir.path().join("src"))?;
+        fs::write(&filepath, "Hello, world!")?;
+
+        let backups = Backups::new(BackupStyle::Dir).root(dir.path().join("backups"));
+        let backup = backups.start();
+        let saved = backup.save(&filepath)?;
+        assert!(saved.starts_with(backup.dir().unwrap()));
+        assert!(saved.ends_with("src/a.txt"));
+        fs::write(&filepath, "Hello, Rust!")?;
+
+        let restored = backups.restore(None, &[])?;
+        assert_eq!(restored, vec![fs::canonicalize(&filepath)?]);
+        assert_eq!(fs::read_to_string(&filepath)?, "Hello, world!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_restore_by_name_only_accepts_backups() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let filepath = dir.path().join("a.txt");
+        fs::write(&filepath, "Hello, world!")?;
+        let backups = Backups::new(BackupStyle::Dir).root(dir.path().join("backups"));
+        let backup = backups.start();
+        backup.save(&filepath)?;
+        fs::write(&filepath, "Hello, Rust!")?;
+        fs::create_dir(dir.path().join("backups/sr-1"))?;
+
+        let name = backup.dir().unwrap().file_name().unwrap().to_str().unwrap();
+        for bad in ["..", "../backups", "sr-1", "other", &format!("./{}", name)] {
+            assert!(backups.restore(Some(bad), &[]).is_err(), "{}", bad);
+        }
+        assert_eq!(fs::read_to_string(&filepath)?, "Hello, Rust!");
+
+        backups.restore(Some(name), &[])?;
+        assert_eq!(fs::read_to_string(&filepath)?, "Hello, world!");
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_keeps_latest_backups() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let filepath = dir.path().join("a.txt");
+        fs::write(&filepath, "")?;
+
+        let backups = Backups::new(BackupStyle::Dir)
+            .root(dir.path().join("backups"))
+            .keep(2);
+        let mut made = vec![];
+        for _ in 0..3 {
+            let backup = backups.start();
+            backup.save(&filepath)?;
+            made.push(backup.dir().unwrap().to_path_buf());
+        }
+        backups.prune()?;
+        assert_eq!(backups.list()?, made[1..]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_keeps_foreign_directories() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let filepath = dir.path().join("a.txt");
+        fs::write(&filepath, "")?;
+        // A root shared with the user's own directories
+        fs::create_dir_all(dir.path().join("2023/photos"))?;
+        fs::create_dir(dir.path().join("sr-1"))?;
+
+        let backups = Backups::new(BackupStyle::Dir).root(dir.path()).keep(1);
+        for _ in 0..2 {
+            backups.start().save(&filepath)?;
+        }
+        backups.prune()?;
+        assert_eq!(backups.list()?.len(), 1);
+        assert!(dir.path().join("2023/photos").is_dir());
+        assert!(dir.path().join("sr-1").is_dir());
+        Ok(())
+    }
+}
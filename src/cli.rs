@@ -0,0 +1,219 @@
+use std::path::PathBuf;
+
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use clap::{ArgGroup, Parser};
+
+use sr_rs::backup::{Backups, DEFAULT_KEEP};
+use sr_rs::enums::backup_enums::BackupStyle;
+use sr_rs::enums::output_enums::OutputFormat;
+use sr_rs::enums::search_enums::MatchEngine;
+use sr_rs::git::GitFilter;
+use sr_rs::search::SearchOptions;
+
+/// tui based search and replace
+#[derive(Debug, Parser)]
+#[command(version, about)]
+#[command(group(ArgGroup::new("printer").args(["format", "json"])))]
+pub struct Cli {
+    /// Pattern to search for
+    #[arg(short, long)]
+    pub search: Option<String>,
+    /// Text to replace matches with
+    #[arg(short, long)]
+    pub replace: Option<String>,
+    /// Treat the pattern as a regular expression, `$1`/`${name}` in the
+    /// replacement refer to its groups
+    #[arg(short = 'E', long)]
+    pub regex: bool,
+    /// Match the pattern's chars in order on a single line, like a fuzzy finder
+    #[arg(long, conflicts_with = "regex")]
+    pub fuzzy: bool,
+    /// Match the pattern as plain text, even if the config picks another
+    /// engine
+    #[arg(long, conflicts_with_all = ["regex", "fuzzy"])]
+    pub literal: bool,
+    /// Match regardless of case
+    #[arg(short, long)]
+    pub ignore_case: bool,
+    /// Match case sensitively, even if the config ignores case
+    #[arg(long, overrides_with = "ignore_case")]
+    pub no_ignore_case: bool,
+    /// Only match whole words
+    #[arg(short, long)]
+    pub word: bool,
+    /// Match within words too, even if the config only matches whole words
+    #[arg(long, overrides_with = "word")]
+    pub no_word: bool,
+    /// Glob of the files to search
+    #[arg(short, long)]
+    pub glob: Option<String>,
+    /// Lines of context shown above and below each match in the preview
+    #[arg(short = 'C', long, value_name = "N")]
+    pub context: Option<usize>,
+    /// Pre-fill the app with this preset of the config file
+    #[arg(long, value_name = "NAME")]
+    pub preset: Option<String>,
+    /// Draw the TUI with this theme: dark, light, high-contrast, no-color or
+    /// one defined in the config
+    #[arg(long, value_name = "NAME")]
+    pub theme: Option<String>,
+    /// Ignore the user and project config files
+    #[arg(long, conflicts_with = "preset")]
+    pub no_config: bool,
+    /// Replace matches in stdin and write the result to stdout, like sed
+    #[arg(long, requires = "search", conflicts_with_all = ["glob", "rg_json", "printer"])]
+    pub filter: bool,
+    /// Use the matches from `rg --json` output in this file, or `-` for
+    /// stdin, instead of searching
+    #[arg(long, value_name = "FILE")]
+    pub rg_json: Option<PathBuf>,
+    /// Apply the search/replace rules in this TOML file instead of a single
+    /// pattern
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["search", "replace", "filter", "rg_json"])]
+    pub rules: Option<PathBuf>,
+    /// Only search files tracked by git
+    #[arg(long, conflicts_with_all = ["filter", "rg_json", "rules"])]
+    pub git_tracked: bool,
+    /// Only search files changed since this git ref, including uncommitted
+    /// changes
+    #[arg(long, value_name = "REF", conflicts_with_all = ["filter", "rg_json", "rules"])]
+    pub git_changed_since: Option<String>,
+    /// Refuse to replace in files with uncommitted changes in git, so
+    /// `git diff` shows only the replacements. `R` replaces anyway
+    #[arg(long, conflicts_with_all = ["filter", "printer"])]
+    pub git_committed: bool,
+    /// Save the original of each file before replacing in it
+    #[arg(
+        long,
+        value_name = "STYLE",
+        value_parser = backup_style_parser(),
+        conflicts_with_all = ["filter", "printer"]
+    )]
+    pub backup: Option<BackupStyle>,
+    /// Directory the timestamped backup directories are created in
+    #[arg(long, value_name = "DIR")]
+    pub backup_dir: Option<PathBuf>,
+    /// Number of backup directories to keep, older ones are removed
+    #[arg(long, value_name = "N", default_value_t = DEFAULT_KEEP)]
+    pub backup_keep: usize,
+    /// Put the files of the latest backup directory, or of the one with this
+    /// name, back in place. With `--backup orig`, restores the `.orig`
+    /// backups of the files matching the glob instead
+    #[arg(
+        long,
+        value_name = "NAME",
+        conflicts_with_all = ["search", "replace", "filter", "rg_json", "rules", "watch", "printer"]
+    )]
+    pub restore: Option<Option<String>>,
+    /// Reopen the session saved in this file, searching again, and save the
+    /// session there. Without it, `s` saves to `session.json` in
+    /// `$XDG_STATE_HOME/sr`
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["filter", "rg_json", "rules", "restore", "printer"]
+    )]
+    pub session: Option<PathBuf>,
+    /// Follow changes to the searched files on disk, updating the matches
+    #[arg(long, conflicts_with_all = ["filter", "printer"])]
+    pub watch: bool,
+    /// Print the matches in this format instead of starting the TUI
+    #[arg(short, long, value_parser = output_format_parser())]
+    pub format: Option<OutputFormat>,
+    /// Print the matches as JSON Lines instead of starting the TUI
+    #[arg(long)]
+    pub json: bool,
+    /// Write printed matches to this file instead of stdout
+    #[arg(short, long, requires = "printer")]
+    pub output: Option<PathBuf>,
+}
+
+impl Cli {
+    /// The search options of the config, with the ones set by flags
+    /// replacing them.
+    pub fn search_options(&self, config: SearchOptions) -> SearchOptions {
+        SearchOptions {
+            engine: if self.regex {
+                MatchEngine::Regex
+            } else if self.fuzzy {
+                MatchEngine::Fuzzy
+            } else if self.literal {
+                MatchEngine::Literal
+            } else {
+                config.engine
+            },
+            ignore_case: flag(self.ignore_case, self.no_ignore_case, config.ignore_case),
+            whole_word: flag(self.word, self.no_word, config.whole_word),
+        }
+    }
+
+    /// The backups configured by the backup flags, in the `dir` style unless
+    /// another is given.
+    pub fn backups(&self) -> Backups {
+        let backups = Backups::new(self.backup.unwrap_or_default()).keep(self.backup_keep);
+        match &self.backup_dir {
+            Some(dir) => backups.root(dir),
+            None => backups,
+        }
+    }
+
+    pub fn git_filter(&self) -> GitFilter {
+        GitFilter {
+            tracked_only: self.git_tracked,
+            changed_since: self.git_changed_since.clone(),
+        }
+    }
+}
+
+/// A flag turned on or off on the command line, or the config's value.
+fn flag(on: bool, off: bool, config: bool) -> bool {
+    match (on, off) {
+        (true, _) => true,
+        (_, true) => false,
+        _ => config,
+    }
+}
+
+/// The backup styles, listed with their help.
+fn backup_style_parser() -> impl TypedValueParser<Value = BackupStyle> {
+    PossibleValuesParser::new([
+        PossibleValue::new("orig")
+            .help("Next to the file, as `file.orig`, overwriting the previous backup"),
+        PossibleValue::new("dir")
+            .help("In a timestamped directory per replace, mirroring the tree"),
+    ])
+    .map(|s| s.parse::<BackupStyle>().expect("a listed backup style"))
+}
+
+/// The output formats, listed with their help.
+fn output_format_parser() -> impl TypedValueParser<Value = OutputFormat> {
+    PossibleValuesParser::new([
+        PossibleValue::new("quickfix")
+            .aliases(["grep", "vimgrep"])
+            .help("`file:line:col:text`, read by Vim's `:cfile` and most grep tooling"),
+        PossibleValue::new("emacs")
+            .help("`file:line:col: text`, the GNU format read by Emacs' compilation mode"),
+    ])
+    .map(|s| s.parse::<OutputFormat>().expect("a listed output format"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flags_override_config_options() {
+        let config = SearchOptions {
+            engine: MatchEngine::Regex,
+            ignore_case: true,
+            whole_word: true,
+        };
+        let cli = Cli::parse_from(["sr", "--literal", "--no-ignore-case", "--no-word"]);
+        assert_eq!(cli.search_options(config), SearchOptions::default());
+
+        let cli = Cli::parse_from(["sr", "--no-word", "-w"]);
+        assert!(cli.search_options(SearchOptions::default()).whole_word);
+        let cli = Cli::parse_from(["sr"]);
+        assert_eq!(cli.search_options(config), config);
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    collections::HashMap,
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+
+use crate::keymap::KeyBindings;
+use crate::theme::ThemeSpec;
+pub use sr_rs::dirs::state_dir;
+use sr_rs::enums::search_enums::MatchEngine;
+use sr_rs::search::SearchOptions;
+
+/// Name of the per-project config file, looked up from the working directory
+/// upwards.
+const PROJECT_CONFIG: &str = ".sr.toml";
+
+/// Defaults for every launch, read from the user's config file and the
+/// project's `.sr.toml`, the latter taking precedence:
+///
+/// ```toml
+/// glob = "src/**/*.rs"
+/// ignore_case = true
+/// context_lines = 2
+/// theme = "solarized"
+///
+/// [preset.rust-rename]
+/// search = 'fn (\w+)_old'
+/// replace = "fn ${1}_new"
+/// engine = "regex"
+///
+/// [preset.todo-cleanup]
+/// search = "TODO"
+/// replace = "FIXME"
+/// glob = "**/*"
+///
+/// [keys.normal]
+/// replace = "<C-r>"
+///
+/// [themes.solarized]
+/// base = "light"
+/// added = { fg = "#859900" }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Config {
+    #[serde(flatten)]
+    pub defaults: Settings,
+    /// Settings applied on top of the defaults with `--preset NAME`
+    #[serde(default, rename = "preset")]
+    pub presets: HashMap<String, Settings>,
+    /// Keys rebound in each input mode
+    #[serde(default)]
+    pub keys: KeyBindings,
+    /// Name of the theme, built-in or user-defined
+    #[serde(default)]
+    pub theme: Option<String>,
+    /// User-defined themes
+    #[serde(default)]
+    pub themes: HashMap<String, ThemeSpec>,
+}
+
+/// Values pre-filling the app, all optional so that config files can be
+/// layered.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+pub struct Settings {
+    pub search: Option<String>,
+    pub replace: Option<String>,
+    pub glob: Option<String>,
+    pub engine: Option<MatchEngine>,
+    pub ignore_case: Option<bool>,
+    pub whole_word: Option<bool>,
+    /// Lines shown above and below each match in the preview
+    pub context_lines: Option<usize>,
+}
+
+impl Settings {
+    /// The settings with the ones set in `over` replacing them.
+    pub fn merge(self, over: Settings) -> Settings {
+        Settings {
+            search: over.search.or(self.search),
+            replace: over.replace.or(self.replace),
+            glob: over.glob.or(self.glob),
+            engine: over.engine.or(self.engine),
+            ignore_case: over.ignore_case.or(self.ignore_case),
+            whole_word: over.whole_word.or(self.whole_word),
+            context_lines: over.context_lines.or(self.context_lines),
+        }
+    }
+
+    pub fn search_options(&self) -> SearchOptions {
+        SearchOptions {
+            engine: self.engine.unwrap_or_default(),
+            ignore_case: self.ignore_case.unwrap_or_default(),
+            whole_word: self.whole_word.unwrap_or_default(),
+        }
+    }
+}
+
+impl Config {
+    /// Parses a config from the contents of a config file.
+    pub fn from_toml(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    /// Reads a config file.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        Self::from_toml(&contents).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    /// Reads the user's config file and the project's, if they exist.
+    pub fn discover() -> io::Result<Self> {
+        let project = env::current_dir()?
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_CONFIG))
+            .find(|path| path.is_file());
+        let mut config = Config::default();
+        for path in user_config_path().into_iter().chain(project) {
+            if path.is_file() {
+                config = config.merge(Self::load(&path)?);
+            }
+        }
+        Ok(config)
+    }
+
+    /// The config with the defaults, presets, keys and themes of `over`
+    /// replacing its own.
+    pub fn merge(mut self, over: Config) -> Config {
+        self.presets.extend(over.presets);
+        self.themes.extend(over.themes);
+        Config {
+            defaults: self.defaults.merge(over.defaults),
+            presets: self.presets,
+            keys: self.keys.merge(over.keys),
+            theme: over.theme.or(self.theme),
+            themes: self.themes,
+        }
+    }
+
+    /// The defaults with the preset applied, if any.
+    pub fn settings(&self, preset: Option<&str>) -> io::Result<Settings> {
+        let Some(name) = preset else {
+            return Ok(self.defaults.clone());
+        };
+        match self.presets.get(name) {
+            Some(preset) => Ok(self.defaults.clone().merge(preset.clone())),
+            None => {
+                let mut names: Vec<&str> = self.presets.keys().map(String::as_str).collect();
+                names.sort_unstable();
+                Err(io::Error::other(format!(
+                    "unknown preset {:?}, the config defines: {}",
+                    name,
+                    names.join(", ")
+                )))
+            }
+        }
+    }
+}
+
+/// `$XDG_CONFIG_HOME/sr/config.toml`, falling back to `~/.config`.
+fn user_config_path() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(env::var_os("HOME")?).join(".config")))?;
+    Some(config_home.join("sr/config.toml"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_config_overrides_user_config() {
+        let user = Config::from_toml(
+            r#"
+            glob = "**/*"
+            ignore_case = true
+
+            [preset.todo-cleanup]
+            search = "TODO"
+            "#,
+        )
+        .unwrap();
+        let project = Config::from_toml(
+            r#"
+            glob = "src/**/*.rs"
+            engine = "regex"
+
+            [preset.rust-rename]
+            search = 'fn (\w+)_old'
+            replace = "fn ${1}_new"
+            "#,
+        )
+        .unwrap();
+        let config = user.merge(project);
+
+        let settings = config.settings(None).unwrap();
+        assert_eq!(settings.glob.as_deref(), Some("src/**/*.rs"));
+        assert_eq!(
+            settings.search_options(),
+            SearchOptions {
+                engine: MatchEngine::Regex,
+                ignore_case: true,
+                whole_word: false,
+            }
+        );
+
+        let settings = config.settings(Some("rust-rename")).unwrap();
+        assert_eq!(settings.replace.as_deref(), Some("fn ${1}_new"));
+        assert_eq!(settings.glob.as_deref(), Some("src/**/*.rs"));
+        assert!(config.settings(Some("todo-cleanup")).is_ok());
+        assert!(config.settings(Some("missing")).is_err());
+    }
+}
@@ -0,0 +1,16 @@
+//! Where sr keeps its own files, out of the files it searches.
+
+use std::{env, path::PathBuf};
+
+/// `$XDG_STATE_HOME`, falling back to `~/.local/state`.
+pub fn state_home() -> Option<PathBuf> {
+    env::var_os("XDG_STATE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(env::var_os("HOME")?).join(".local/state")))
+}
+
+/// `$XDG_STATE_HOME/sr`, where the history, sessions and backups are kept.
+pub fn state_dir() -> Option<PathBuf> {
+    Some(state_home()?.join("sr"))
+}
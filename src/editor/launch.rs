@@ -0,0 +1,138 @@
+use std::{env, io, path::Path, process::Command};
+
+use crate::models::match_struct::Match;
+
+/// Editor used when neither `$VISUAL` nor `$EDITOR` is set
+const DEFAULT_EDITOR: &str = "vi";
+
+/// Opens the file of the match in the user's editor, with the cursor on the
+/// match. Blocks until the editor exits.
+pub fn open_in_editor(m: &Match) -> io::Result<()> {
+    let editor = env::var("VISUAL")
+        .or_else(|_| env::var("EDITOR"))
+        .unwrap_or_else(|_| DEFAULT_EDITOR.into());
+    let mut command = editor_command(&editor, m)?;
+
+    log::info!("Opening editor: {:?}", command);
+    let status = command.status()?;
+    if !status.success() {
+        log::error!("Editor exited with {}", status);
+    }
+    Ok(())
+}
+
+/// Builds the command that opens the file of the match at its line and
+/// column. Editors that don't take a column are only given the line.
+fn editor_command(editor: &str, m: &Match) -> io::Result<Command> {
+    // Lines and columns are 0-based in a Match, editors count from 1
+    let filepath = m.get_filepath();
+    let line = m.get_line_num() + 1;
+    let column = m.get_start_on_line() + 1;
+    // $EDITOR may carry arguments, e.g. "code --wait"
+    let mut parts = editor.split_whitespace();
+    let program = parts
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "editor command is empty"))?;
+    let mut command = Command::new(program);
+    command.args(parts);
+
+    let name = Path::new(program)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(program);
+    match name {
+        "vim" | "nvim" | "gvim" | "mvim" => {
+            // cursor() takes a byte column
+            let byte_column = m.get_byte_start_on_line() + 1;
+            command.arg(format!("+call cursor({}, {})", line, byte_column));
+            command.arg(filepath);
+        }
+        "emacs" | "emacsclient" | "kak" => {
+            command.arg(format!("+{}:{}", line, column));
+            command.arg(filepath);
+        }
+        "nano" => {
+            command.arg(format!("+{},{}", line, column));
+            command.arg(filepath);
+        }
+        "code" | "code-insiders" | "codium" => {
+            command.arg("--goto");
+            command.arg(format!("{}:{}:{}", filepath, line, column));
+        }
+        "hx" | "helix" | "subl" | "zed" => {
+            command.arg(format!("{}:{}:{}", filepath, line, column));
+        }
+        // Including plain vi, which is often nvi or busybox without `:call`
+        _ => {
+            command.arg(format!("+{}", line));
+            command.arg(filepath);
+        }
+    }
+    Ok(command)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(command: &Command) -> Vec<String> {
+        command
+            .get_args()
+            .map(|arg| arg.to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// A match in src/main.rs on line 3, starting at char 6 of the line.
+    fn match_on(line: &str) -> Match {
+        Match::new(
+            "src/main.rs".into(),
+            0,
+            1,
+            6,
+            7,
+            String::new(),
+            line.into(),
+            2,
+        )
+    }
+
+    #[test]
+    fn test_editor_command_vim() {
+        let command = editor_command("/usr/bin/nvim", &match_on("let x = 1;")).unwrap();
+        assert_eq!(command.get_program(), "/usr/bin/nvim");
+        assert_eq!(args(&command), vec!["+call cursor(3, 7)", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_editor_command_non_ascii_line() {
+        let m = match_on("// éü x");
+        let command = editor_command("vim", &m).unwrap();
+        assert_eq!(args(&command), vec!["+call cursor(3, 9)", "src/main.rs"]);
+        let command = editor_command("emacs", &m).unwrap();
+        assert_eq!(args(&command), vec!["+3:7", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_editor_command_with_arguments() {
+        let command = editor_command("code --wait", &match_on("let x = 1;")).unwrap();
+        assert_eq!(command.get_program(), "code");
+        assert_eq!(args(&command), vec!["--wait", "--goto", "src/main.rs:3:7"]);
+    }
+
+    #[test]
+    fn test_editor_command_unknown_editor_gets_line_only() {
+        let command = editor_command("ed", &match_on("let x = 1;")).unwrap();
+        assert_eq!(args(&command), vec!["+3", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_editor_command_vi_gets_line_only() {
+        let command = editor_command("vi", &match_on("let x = 1;")).unwrap();
+        assert_eq!(args(&command), vec!["+3", "src/main.rs"]);
+    }
+
+    #[test]
+    fn test_editor_command_empty() {
+        assert!(editor_command("  ", &match_on("let x = 1;")).is_err());
+    }
+}
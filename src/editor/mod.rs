@@ -0,0 +1,85 @@
+pub mod launch;
+pub mod nvim;
+pub mod vim;
+pub use launch::open_in_editor;
+
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use nvim::NvimClient;
+use vim::modified_swap_file;
+
+/// A file that is open with unsaved changes in an editor, which replacing in
+/// would clobber.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnsavedChanges {
+    pub filepath: PathBuf,
+    /// Where the unsaved changes were found
+    pub source: String,
+}
+
+impl fmt::Display for UnsavedChanges {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} has unsaved changes ({})",
+            self.filepath.display(),
+            self.source
+        )
+    }
+}
+
+/// Checks the files for unsaved changes in Vim, through their swap files, and
+/// in the Neovim sr is running in, through its RPC socket when `$NVIM` is set.
+pub fn find_unsaved_changes(filepaths: &[PathBuf]) -> Vec<UnsavedChanges> {
+    let nvim_modified: Vec<PathBuf> = match NvimClient::from_env() {
+        Some(Ok(mut client)) => client.modified_buffers().unwrap_or_else(|e| {
+            log::error!("Failed to list modified Neovim buffers: {}", e);
+            vec![]
+        }),
+        Some(Err(e)) => {
+            log::error!("Failed to connect to Neovim: {}", e);
+            vec![]
+        }
+        None => vec![],
+    };
+    let nvim_modified: Vec<PathBuf> = nvim_modified.iter().map(|p| canonical(p)).collect();
+
+    let mut unsaved = vec![];
+    for filepath in filepaths {
+        if let Some(swap) = modified_swap_file(filepath) {
+            unsaved.push(UnsavedChanges {
+                filepath: filepath.clone(),
+                source: format!("Vim swap file {}", swap.display()),
+            });
+        } else if nvim_modified.contains(&canonical(filepath)) {
+            unsaved.push(UnsavedChanges {
+                filepath: filepath.clone(),
+                source: "modified buffer in Neovim".into(),
+            });
+        }
+    }
+    unsaved
+}
+
+/// Tells Neovim to reload the buffers of files sr has written, when sr can
+/// reach it through `$SR_NVIM_SOCKET` or `$NVIM`.
+pub fn reload_nvim_buffers(filepaths: &[PathBuf]) {
+    if filepaths.is_empty() {
+        return;
+    }
+    match NvimClient::from_env() {
+        Some(Ok(mut client)) => match client.reload_buffers(filepaths) {
+            Ok(reloaded) => log::info!("Reloaded {} Neovim buffers", reloaded),
+            Err(e) => log::error!("Failed to reload Neovim buffers: {}", e),
+        },
+        Some(Err(e)) => log::error!("Failed to connect to Neovim: {}", e),
+        None => {}
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
@@ -0,0 +1,305 @@
+use std::{
+    env, fs,
+    io::{self, BufReader, Read, Write},
+    net::TcpStream,
+    path::PathBuf,
+    time::Duration,
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+
+use rmpv::Value;
+
+/// How long to wait for Neovim before giving up on a request
+const RPC_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// msgpack-RPC message types
+const REQUEST: u64 = 0;
+const RESPONSE: u64 = 1;
+
+/// Lua run inside Neovim to list the names of buffers with unsaved changes
+const MODIFIED_BUFFERS_LUA: &str = "
+local names = {}
+for _, buf in ipairs(vim.api.nvim_list_bufs()) do
+    if vim.api.nvim_buf_is_loaded(buf) and vim.bo[buf].modified then
+        table.insert(names, vim.api.nvim_buf_get_name(buf))
+    end
+end
+return names
+";
+
+/// Lua run inside Neovim to re-read the buffers of the given files from disk
+const RELOAD_BUFFERS_LUA: &str = "
+local wanted = {}
+for _, path in ipairs(...) do
+    wanted[path] = true
+end
+local reloaded = 0
+for _, buf in ipairs(vim.api.nvim_list_bufs()) do
+    if vim.api.nvim_buf_is_loaded(buf) and wanted[vim.api.nvim_buf_get_name(buf)] then
+        vim.cmd('checktime ' .. buf)
+        reloaded = reloaded + 1
+    end
+end
+return reloaded
+";
+
+/// Socket sr should use instead of `$NVIM`
+const SOCKET_ENV_VAR: &str = "SR_NVIM_SOCKET";
+
+trait Stream: Read + Write {}
+impl<T: Read + Write> Stream for T {}
+
+/// A minimal msgpack-RPC client for talking to a running Neovim.
+pub struct NvimClient {
+    reader: BufReader<Box<dyn Stream>>,
+    next_id: u64,
+}
+
+impl NvimClient {
+    /// Connects to the Neovim listening on `address`, either a unix socket
+    /// path or a `host:port` TCP address.
+    pub fn connect(address: &str) -> io::Result<Self> {
+        let stream: Box<dyn Stream> = if is_tcp_address(address) {
+            let stream = TcpStream::connect(address)?;
+            stream.set_read_timeout(Some(RPC_TIMEOUT))?;
+            Box::new(stream)
+        } else {
+            connect_socket(address)?
+        };
+
+        Ok(Self {
+            reader: BufReader::new(stream),
+            next_id: 0,
+        })
+    }
+
+    /// Connects to the socket configured in `$SR_NVIM_SOCKET`, or else to the
+    /// Neovim sr is running inside of, if any. Neovim sets `$NVIM` for
+    /// processes started from its terminal.
+    pub fn from_env() -> Option<io::Result<Self>> {
+        let address = [SOCKET_ENV_VAR, "NVIM"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|address| !address.is_empty())?;
+        Some(Self::connect(&address))
+    }
+
+    /// Calls an API method and waits for its result.
+    pub fn call(&mut self, method: &str, params: Vec<Value>) -> io::Result<Value> {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let request = Value::Array(vec![
+            REQUEST.into(),
+            id.into(),
+            method.into(),
+            Value::Array(params),
+        ]);
+        rmpv::encode::write_value(self.reader.get_mut(), &request)?;
+        self.reader.get_mut().flush()?;
+
+        loop {
+            let message = rmpv::decode::read_value(&mut self.reader)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            // Skip notifications and responses to other requests
+            let Value::Array(parts) = message else {
+                continue;
+            };
+            if parts.len() != 4
+                || parts[0].as_u64() != Some(RESPONSE)
+                || parts[1].as_u64() != Some(id)
+            {
+                continue;
+            }
+
+            let mut parts = parts.into_iter().skip(2);
+            let error = parts.next().unwrap_or(Value::Nil);
+            let result = parts.next().unwrap_or(Value::Nil);
+            if !error.is_nil() {
+                return Err(io::Error::other(format!("{} failed: {}", method, error)));
+            }
+            return Ok(result);
+        }
+    }
+
+    /// Paths of the buffers that have unsaved changes.
+    pub fn modified_buffers(&mut self) -> io::Result<Vec<PathBuf>> {
+        let result = self.call(
+            "nvim_exec_lua",
+            vec![MODIFIED_BUFFERS_LUA.into(), Value::Array(vec![])],
+        )?;
+        let names = match result {
+            Value::Array(names) => names,
+            _ => vec![],
+        };
+
+        Ok(names
+            .iter()
+            .filter_map(|name| name.as_str())
+            .filter(|name| !name.is_empty())
+            .map(PathBuf::from)
+            .collect())
+    }
+
+    /// Makes Neovim re-read the buffers of the given files, so they don't go
+    /// stale after sr writes them. Returns the number of buffers reloaded.
+    pub fn reload_buffers(&mut self, filepaths: &[PathBuf]) -> io::Result<u64> {
+        let paths: Vec<Value> = filepaths
+            .iter()
+            .map(|path| {
+                // Neovim names buffers by their absolute path
+                let path = fs::canonicalize(path).unwrap_or_else(|_| path.clone());
+                path.to_string_lossy().into_owned().into()
+            })
+            .collect();
+        let result = self.call(
+            "nvim_exec_lua",
+            vec![
+                RELOAD_BUFFERS_LUA.into(),
+                Value::Array(vec![Value::Array(paths)]),
+            ],
+        )?;
+        Ok(result.as_u64().unwrap_or(0))
+    }
+}
+
+fn is_tcp_address(address: &str) -> bool {
+    !address.contains('/') && address.contains(':')
+}
+
+#[cfg(unix)]
+fn connect_socket(address: &str) -> io::Result<Box<dyn Stream>> {
+    let stream = UnixStream::connect(address)?;
+    stream.set_read_timeout(Some(RPC_TIMEOUT))?;
+    Ok(Box::new(stream))
+}
+
+#[cfg(not(unix))]
+fn connect_socket(address: &str) -> io::Result<Box<dyn Stream>> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "can't connect to {}, only TCP addresses are supported",
+            address
+        ),
+    ))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixListener;
+    use std::path::Path;
+    use std::thread::{self, JoinHandle};
+
+    /// Starts a fake Neovim that answers every request with `respond` and
+    /// returns the requests it received once the client disconnects.
+    fn mock_nvim<F>(socket: &Path, respond: F) -> JoinHandle<Vec<(String, Vec<Value>)>>
+    where
+        F: Fn(&str, &[Value]) -> Value + Send + 'static,
+    {
+        let listener = UnixListener::bind(socket).unwrap();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut writer = stream;
+            let mut requests = vec![];
+
+            while let Ok(Value::Array(parts)) = rmpv::decode::read_value(&mut reader) {
+                let id = parts[1].clone();
+                let method = parts[2].as_str().unwrap().to_string();
+                let params = parts[3].as_array().cloned().unwrap_or_default();
+
+                // Interleave a notification to make sure the client skips it
+                let notification = Value::Array(vec![
+                    2.into(),
+                    "nvim_buf_lines_event".into(),
+                    Value::Array(vec![]),
+                ]);
+                rmpv::encode::write_value(&mut writer, &notification).unwrap();
+
+                let result = respond(&method, &params);
+                let response = Value::Array(vec![RESPONSE.into(), id, Value::Nil, result]);
+                rmpv::encode::write_value(&mut writer, &response).unwrap();
+                requests.push((method, params));
+            }
+            requests
+        })
+    }
+
+    #[test]
+    fn test_modified_buffers() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("nvim.sock");
+        let server = mock_nvim(&socket, |_, _| {
+            Value::Array(vec!["/tmp/a.txt".into(), "".into(), "/tmp/b.txt".into()])
+        });
+
+        let mut client = NvimClient::connect(socket.to_str().unwrap()).unwrap();
+        let modified = client.modified_buffers().unwrap();
+        drop(client);
+
+        assert_eq!(
+            modified,
+            vec![PathBuf::from("/tmp/a.txt"), PathBuf::from("/tmp/b.txt")]
+        );
+        let requests = server.join().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].0, "nvim_exec_lua");
+    }
+
+    #[test]
+    fn test_reload_buffers() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("nvim.sock");
+        let file_path = dir.path().join("test.txt");
+        std::fs::write(&file_path, "Hello, world!").unwrap();
+        let server = mock_nvim(&socket, |_, _| Value::from(1));
+
+        let mut client = NvimClient::connect(socket.to_str().unwrap()).unwrap();
+        let reloaded = client
+            .reload_buffers(std::slice::from_ref(&file_path))
+            .unwrap();
+        drop(client);
+
+        assert_eq!(reloaded, 1);
+        let requests = server.join().unwrap();
+        assert_eq!(requests[0].0, "nvim_exec_lua");
+        let expected_path = std::fs::canonicalize(&file_path).unwrap();
+        assert_eq!(
+            requests[0].1[1],
+            Value::Array(vec![Value::Array(vec![expected_path
+                .to_str()
+                .unwrap()
+                .into()])])
+        );
+    }
+
+    #[test]
+    fn test_call_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("nvim.sock");
+        let listener = UnixListener::bind(&socket).unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let request = rmpv::decode::read_value(&mut stream).unwrap();
+            let id = request.as_array().unwrap()[1].clone();
+            let error = Value::Array(vec![0.into(), "Invalid method".into()]);
+            let response = Value::Array(vec![RESPONSE.into(), id, error, Value::Nil]);
+            rmpv::encode::write_value(&mut stream, &response).unwrap();
+        });
+
+        let mut client = NvimClient::connect(socket.to_str().unwrap()).unwrap();
+        let err = client.call("nvim_nope", vec![]).unwrap_err();
+        assert!(err.to_string().contains("nvim_nope failed"));
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn test_is_tcp_address() {
+        assert!(is_tcp_address("127.0.0.1:6666"));
+        assert!(!is_tcp_address("/run/user/1000/nvim.1234.0"));
+    }
+}
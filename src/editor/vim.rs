@@ -0,0 +1,131 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use crate::dirs::state_home;
+
+/// Size of the header ("block 0") at the start of every Vim swap file
+const BLOCK0_SIZE: usize = 1008;
+/// Offset of the dirty flag, the last byte of the `b0_fname` field
+const B0_DIRTY_OFFSET: usize = 1007;
+/// Value of the dirty flag when the buffer has unsaved changes
+const B0_DIRTY: u8 = 0x55;
+
+/// Looks for a Vim/Neovim swap file belonging to `path` whose buffer has
+/// unsaved changes, returning the swap file if one is found.
+pub fn modified_swap_file(path: &Path) -> Option<PathBuf> {
+    swap_file_candidates(path)
+        .into_iter()
+        .find(|swap| is_modified_swap_file(swap).unwrap_or(false))
+}
+
+/// Lists the swap files that may belong to `path`: `.name.swp`, `.name.swo`,
+/// ... next to the file, and the `%`-encoded ones in Neovim's swap directory.
+fn swap_file_candidates(path: &Path) -> Vec<PathBuf> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return vec![];
+    };
+    let name = name.to_string_lossy();
+    // Vim doesn't add a second dot for hidden files
+    let swap_name = if name.starts_with('.') {
+        name.to_string()
+    } else {
+        format!(".{}", name)
+    };
+
+    let mut candidates = vec![];
+    // Vim tries .swp first and counts down to .swa when it's taken
+    for last in ('a'..='p').rev() {
+        candidates.push(dir.join(format!("{}.sw{}", swap_name, last)));
+    }
+
+    if let (Some(state_dir), Ok(absolute)) = (nvim_swap_dir(), fs::canonicalize(path)) {
+        let encoded = absolute.to_string_lossy().replace('/', "%");
+        for last in ('a'..='p').rev() {
+            candidates.push(state_dir.join(format!("{}.sw{}", encoded, last)));
+        }
+    }
+
+    candidates.retain(|candidate| candidate.is_file());
+    candidates
+}
+
+/// Neovim keeps its swap files under `$XDG_STATE_HOME/nvim/swap`.
+fn nvim_swap_dir() -> Option<PathBuf> {
+    Some(state_home()?.join("nvim").join("swap"))
+}
+
+/// Reads the header of a swap file and checks its dirty flag.
+fn is_modified_swap_file(swap: &Path) -> io::Result<bool> {
+    let mut header = [0u8; BLOCK0_SIZE];
+    fs::File::open(swap)?.read_exact(&mut header)?;
+
+    // Every swap file starts with the block 0 id "b0"
+    if &header[..2] != b"b0" {
+        return Ok(false);
+    }
+    Ok(header[B0_DIRTY_OFFSET] == B0_DIRTY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn write_swap_file(path: &Path, dirty: bool) {
+        let mut header = vec![0u8; 4096];
+        header[..2].copy_from_slice(b"b0");
+        header[2..9].copy_from_slice(b"VIM 9.0");
+        if dirty {
+            header[B0_DIRTY_OFFSET] = B0_DIRTY;
+        }
+        fs::write(path, header).unwrap();
+    }
+
+    #[test]
+    fn test_modified_swap_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, "Hello, world!").unwrap();
+        write_swap_file(&dir.path().join(".test.txt.swp"), true);
+
+        assert_eq!(
+            modified_swap_file(&file_path),
+            Some(dir.path().join(".test.txt.swp"))
+        );
+    }
+
+    #[test]
+    fn test_unmodified_swap_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, "Hello, world!").unwrap();
+        write_swap_file(&dir.path().join(".test.txt.swp"), false);
+
+        assert_eq!(modified_swap_file(&file_path), None);
+    }
+
+    #[test]
+    fn test_swap_file_of_hidden_file() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join(".bashrc");
+        fs::write(&file_path, "export A=1").unwrap();
+        write_swap_file(&dir.path().join(".bashrc.swo"), true);
+
+        assert_eq!(
+            modified_swap_file(&file_path),
+            Some(dir.path().join(".bashrc.swo"))
+        );
+    }
+
+    #[test]
+    fn test_not_a_swap_file() {
+        let dir = tempdir().unwrap();
+        let swap = dir.path().join(".test.txt.swp");
+        fs::write(&swap, vec![B0_DIRTY; 2048]).unwrap();
+
+        assert!(!is_modified_swap_file(&swap).unwrap());
+    }
+}
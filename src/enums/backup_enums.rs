@@ -0,0 +1,23 @@
+use std::str::FromStr;
+
+/// Where the original of a file is saved before replacing in it.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum BackupStyle {
+    /// Next to the file, as `file.orig`, overwriting the previous backup
+    Orig,
+    /// In a timestamped directory per replace, mirroring the tree
+    #[default]
+    Dir,
+}
+
+impl FromStr for BackupStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "orig" => Ok(Self::Orig),
+            "dir" => Ok(Self::Dir),
+            _ => Err(format!("unknown backup style {:?}", s)),
+        }
+    }
+}
@@ -1 +1,3 @@
-pub mod input_enums;
+pub mod backup_enums;
+pub mod output_enums;
+pub mod search_enums;
@@ -0,0 +1,22 @@
+use std::str::FromStr;
+
+/// Formats matches can be printed in instead of starting the TUI.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OutputFormat {
+    /// `file:line:col:text`, read by Vim's `:cfile` and most grep tooling
+    Quickfix,
+    /// `file:line:col: text`, the GNU format read by Emacs' compilation mode
+    Emacs,
+}
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "quickfix" | "grep" | "vimgrep" => Ok(Self::Quickfix),
+            "emacs" => Ok(Self::Emacs),
+            _ => Err(format!("unknown output format {:?}", s)),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use serde::{Deserialize, Serialize};
+
+/// Where the app's matches come from.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchSource {
+    /// sr's own search over the Filepath glob
+    Search,
+    /// Matches imported from `rg --json` output
+    Ripgrep,
+    /// Matches of the rules in a rules file
+    Rules,
+}
+
+/// The engine used to match the search pattern.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchEngine {
+    /// The pattern is plain text
+    #[default]
+    Literal,
+    /// The pattern is a regular expression, replacements can refer to groups
+    Regex,
+    /// The pattern's chars must appear in order on a single line
+    Fuzzy,
+}
+
+impl MatchEngine {
+    /// Returns the next engine in the sequence.
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Literal => Self::Regex,
+            Self::Regex => Self::Fuzzy,
+            Self::Fuzzy => Self::Literal,
+        }
+    }
+}
+
+/// What the Search/Replace pair is applied to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplaceTarget {
+    /// The contents of the files matching the glob
+    #[default]
+    Contents,
+    /// The paths of the files and directories matching the glob
+    Paths,
+    /// Both contents and paths, in a single refactor
+    Both,
+}
+
+impl ReplaceTarget {
+    /// Returns the next target in the sequence.
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Contents => Self::Paths,
+            Self::Paths => Self::Both,
+            Self::Both => Self::Contents,
+        }
+    }
+
+    /// Whether file contents are searched and replaced.
+    pub fn includes_contents(&self) -> bool {
+        matches!(self, Self::Contents | Self::Both)
+    }
+
+    /// Whether paths are searched and renamed.
+    pub fn includes_paths(&self) -> bool {
+        matches!(self, Self::Paths | Self::Both)
+    }
+}
@@ -0,0 +1,67 @@
+use sr_rs::models::match_struct::Match;
+
+/// All matches found in a single file, shown as one section of the preview.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileGroup {
+    pub filepath: String,
+    /// Indices into the match list this group was built from
+    pub match_indices: Vec<usize>,
+}
+
+/// A single visible row of the preview tree.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PreviewRow {
+    /// Header of the file group at this index
+    File(usize),
+    /// The match at this index of the match list
+    Match(usize),
+}
+
+/// Groups matches by file, keeping files in the order they were first seen.
+pub fn group_by_file(matches: &[Match]) -> Vec<FileGroup> {
+    let mut groups: Vec<FileGroup> = vec![];
+    for (i, m) in matches.iter().enumerate() {
+        match groups.iter_mut().find(|g| g.filepath == m.get_filepath()) {
+            Some(group) => group.match_indices.push(i),
+            None => groups.push(FileGroup {
+                filepath: m.get_filepath().to_string(),
+                match_indices: vec![i],
+            }),
+        }
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_match(filepath: &str, index: usize) -> Match {
+        Match::new(
+            filepath.into(),
+            index,
+            1,
+            index,
+            index + 1,
+            "".into(),
+            "abc".into(),
+            0,
+        )
+    }
+
+    #[test]
+    fn test_group_by_file_keeps_first_seen_order() {
+        let matches = vec![
+            new_match("b.txt", 0),
+            new_match("a.txt", 0),
+            new_match("b.txt", 2),
+        ];
+
+        let groups = group_by_file(&matches);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].filepath, "b.txt");
+        assert_eq!(groups[0].match_indices, vec![0, 2]);
+        assert_eq!(groups[1].filepath, "a.txt");
+        assert_eq!(groups[1].match_indices, vec![1]);
+    }
+}
@@ -0,0 +1,365 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+/// Limits a search to the files git knows about.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GitFilter {
+    /// Only search files tracked by git
+    pub tracked_only: bool,
+    /// Only search files that changed since this ref, committed or not
+    pub changed_since: Option<String>,
+}
+
+impl GitFilter {
+    pub fn is_active(&self) -> bool {
+        self.tracked_only || self.changed_since.is_some()
+    }
+
+    /// Keeps the paths that pass the filter, and the directories holding a
+    /// file that passes it. Every path must be inside a git repository when
+    /// the filter is active.
+    pub fn apply(&self, paths: Vec<PathBuf>) -> io::Result<Vec<PathBuf>> {
+        if !self.is_active() {
+            return Ok(paths);
+        }
+
+        let mut repos = RepoCache::default();
+        let mut allowed: HashMap<PathBuf, HashSet<PathBuf>> = HashMap::new();
+        let mut kept = vec![];
+        for path in paths {
+            // git's own files are never tracked, and git can't be run in them
+            if path.components().any(|c| c.as_os_str() == ".git") {
+                continue;
+            }
+            let repo = repos.repo_of(&path)?;
+            let files = match allowed.get(&repo.root) {
+                Some(files) => files,
+                None => {
+                    let files = self.allowed_files(&repo)?;
+                    allowed.entry(repo.root.clone()).or_insert(files)
+                }
+            };
+            let canonical_path = canonical(&path);
+            let passes = match path.is_dir() {
+                true => files.iter().any(|file| file.starts_with(&canonical_path)),
+                false => files.contains(&canonical_path),
+            };
+            if passes {
+                kept.push(path);
+            }
+        }
+        Ok(kept)
+    }
+
+    /// The files of the repository passing the filter
+    fn allowed_files(&self, repo: &GitRepo) -> io::Result<HashSet<PathBuf>> {
+        let tracked = match self.tracked_only {
+            true => Some(repo.tracked_files()?),
+            false => None,
+        };
+        let changed = match &self.changed_since {
+            Some(reference) => Some(repo.changed_since(reference)?),
+            None => None,
+        };
+        Ok(match (tracked, changed) {
+            (Some(tracked), Some(changed)) => tracked.intersection(&changed).cloned().collect(),
+            (Some(files), None) | (None, Some(files)) => files,
+            (None, None) => HashSet::new(),
+        })
+    }
+}
+
+/// A git repository, queried by running `git` locally.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GitRepo {
+    /// Absolute path of the working tree
+    pub root: PathBuf,
+}
+
+impl GitRepo {
+    /// Finds the repository containing the directory.
+    pub fn discover(dir: &Path) -> io::Result<Self> {
+        let output = git(dir, &["rev-parse", "--show-toplevel"])?;
+        let root = String::from_utf8_lossy(&output).trim_end().to_string();
+        Ok(Self {
+            root: canonical(Path::new(&root)),
+        })
+    }
+
+    /// Files in the index.
+    pub fn tracked_files(&self) -> io::Result<HashSet<PathBuf>> {
+        let output = git(&self.root, &["ls-files", "-z", "--full-name"])?;
+        Ok(self.paths(&output))
+    }
+
+    /// Files that differ from the ref in the working tree, including changes
+    /// that aren't committed yet.
+    pub fn changed_since(&self, reference: &str) -> io::Result<HashSet<PathBuf>> {
+        let output = git(
+            &self.root,
+            &["diff", "--name-only", "-z", "--no-renames", reference, "--"],
+        )?;
+        Ok(self.paths(&output))
+    }
+
+    /// Files with uncommitted changes, staged or not, and untracked files.
+    pub fn uncommitted_files(&self) -> io::Result<HashSet<PathBuf>> {
+        let output = git(
+            &self.root,
+            &["status", "--porcelain=v1", "-z", "--untracked-files=all"],
+        )?;
+
+        let mut files = HashSet::new();
+        let mut entries = output.split(|&b| b == 0).filter(|e| !e.is_empty());
+        while let Some(entry) = entries.next() {
+            if entry.len() < 4 {
+                continue;
+            }
+            // Entries look like "XY path", renames and copies, staged or not,
+            // are followed by the original path
+            if entry[..2]
+                .iter()
+                .any(|status| matches!(status, b'R' | b'C'))
+            {
+                entries.next();
+            }
+            files.insert(
+                self.root
+                    .join(String::from_utf8_lossy(&entry[3..]).as_ref()),
+            );
+        }
+        Ok(files)
+    }
+
+    /// Absolute paths of the NUL separated, repository relative paths
+    fn paths(&self, output: &[u8]) -> HashSet<PathBuf> {
+        output
+            .split(|&b| b == 0)
+            .filter(|p| !p.is_empty())
+            .map(|p| self.root.join(String::from_utf8_lossy(p).as_ref()))
+            .collect()
+    }
+}
+
+/// A file that has uncommitted changes in git, which replacing in would mix
+/// with the replacements in `git diff`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UncommittedChanges {
+    pub filepath: PathBuf,
+}
+
+impl std::fmt::Display for UncommittedChanges {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} has uncommitted changes in git",
+            self.filepath.display()
+        )
+    }
+}
+
+/// Checks the files for uncommitted changes in the repositories they are in.
+/// Files outside of any repository are never reported.
+pub fn find_uncommitted_changes(filepaths: &[PathBuf]) -> Vec<UncommittedChanges> {
+    let mut repos = RepoCache::default();
+    let mut uncommitted: HashMap<PathBuf, HashSet<PathBuf>> = HashMap::new();
+    let mut found = vec![];
+    for filepath in filepaths {
+        let repo = match repos.repo_of(filepath) {
+            Ok(repo) => repo,
+            Err(e) => {
+                log::warn!("{} isn't in a git repository: {}", filepath.display(), e);
+                continue;
+            }
+        };
+        let files = match uncommitted.get(&repo.root) {
+            Some(files) => files,
+            None => {
+                let files = repo.uncommitted_files().unwrap_or_else(|e| {
+                    log::error!("Failed to read git status: {}", e);
+                    HashSet::new()
+                });
+                uncommitted.entry(repo.root.clone()).or_insert(files)
+            }
+        };
+        if files.contains(&canonical(filepath)) {
+            found.push(UncommittedChanges {
+                filepath: filepath.clone(),
+            });
+        }
+    }
+    found
+}
+
+/// The repositories of directories already looked up
+#[derive(Default)]
+struct RepoCache {
+    repos: HashMap<PathBuf, GitRepo>,
+}
+
+impl RepoCache {
+    fn repo_of(&mut self, path: &Path) -> io::Result<GitRepo> {
+        let path = canonical(path);
+        let dir = match path.parent() {
+            _ if path.is_dir() => path.clone(),
+            Some(dir) => dir.to_path_buf(),
+            None => PathBuf::from("/"),
+        };
+        if let Some(repo) = self.repos.get(&dir) {
+            return Ok(repo.clone());
+        }
+        let repo = GitRepo::discover(&dir)?;
+        self.repos.insert(dir, repo.clone());
+        Ok(repo)
+    }
+}
+
+/// Runs git in the directory, returning its standard output.
+fn git(dir: &Path, args: &[&str]) -> io::Result<Vec<u8>> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a repository with a commit of `committed.txt`
+    fn repo() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let run = |args: &[&str]| git(dir.path(), args).unwrap();
+        run(&["init", "-q"]);
+        run(&["config", "user.email", "sr@example.com"]);
+        run(&["config", "user.name", "sr"]);
+        fs::write(dir.path().join("committed.txt"), "world").unwrap();
+        run(&["add", "committed.txt"]);
+        run(&["commit", "-q", "-m", "initial"]);
+        dir
+    }
+
+    #[test]
+    fn test_tracked_only() {
+        let dir = repo();
+        fs::write(dir.path().join("untracked.txt"), "world").unwrap();
+
+        let filter = GitFilter {
+            tracked_only: true,
+            ..GitFilter::default()
+        };
+        let kept = filter
+            .apply(vec![
+                dir.path().join("committed.txt"),
+                dir.path().join("untracked.txt"),
+            ])
+            .unwrap();
+        assert_eq!(kept, vec![dir.path().join("committed.txt")]);
+    }
+
+    #[test]
+    fn test_tracked_only_keeps_directories_with_tracked_files() {
+        let dir = repo();
+        fs::create_dir(dir.path().join("tracked")).unwrap();
+        fs::write(dir.path().join("tracked/a.txt"), "").unwrap();
+        git(dir.path(), &["add", "tracked/a.txt"]).unwrap();
+        fs::create_dir(dir.path().join("target")).unwrap();
+        fs::write(dir.path().join("target/b.txt"), "").unwrap();
+
+        let filter = GitFilter {
+            tracked_only: true,
+            ..GitFilter::default()
+        };
+        let kept = filter
+            .apply(vec![dir.path().join("tracked"), dir.path().join("target")])
+            .unwrap();
+        assert_eq!(kept, vec![dir.path().join("tracked")]);
+    }
+
+    #[test]
+    fn test_changed_since() {
+        let dir = repo();
+        fs::write(dir.path().join("other.txt"), "world").unwrap();
+        git(dir.path(), &["add", "other.txt"]).unwrap();
+        git(dir.path(), &["commit", "-q", "-m", "other"]).unwrap();
+
+        let filter = GitFilter {
+            changed_since: Some("HEAD~1".into()),
+            ..GitFilter::default()
+        };
+        let kept = filter
+            .apply(vec![
+                dir.path().join("committed.txt"),
+                dir.path().join("other.txt"),
+            ])
+            .unwrap();
+        assert_eq!(kept, vec![dir.path().join("other.txt")]);
+    }
+
+    #[test]
+    fn test_filter_outside_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "").unwrap();
+        let filter = GitFilter {
+            tracked_only: true,
+            ..GitFilter::default()
+        };
+        assert!(filter.apply(vec![dir.path().join("a.txt")]).is_err());
+    }
+
+    #[test]
+    fn test_uncommitted_files_with_unstaged_rename() {
+        let dir = repo();
+        fs::rename(
+            dir.path().join("committed.txt"),
+            dir.path().join("moved.txt"),
+        )
+        .unwrap();
+        git(dir.path(), &["add", "-N", "moved.txt"]).unwrap();
+        fs::write(dir.path().join("untracked.txt"), "").unwrap();
+
+        let repo = GitRepo::discover(dir.path()).unwrap();
+        let root = &repo.root;
+        assert_eq!(
+            repo.uncommitted_files().unwrap(),
+            HashSet::from([root.join("moved.txt"), root.join("untracked.txt")])
+        );
+    }
+
+    #[test]
+    fn test_find_uncommitted_changes() {
+        let dir = repo();
+        fs::write(dir.path().join("clean.txt"), "").unwrap();
+        git(dir.path(), &["add", "clean.txt"]).unwrap();
+        git(dir.path(), &["commit", "-q", "-m", "clean"]).unwrap();
+        fs::write(dir.path().join("committed.txt"), "changed").unwrap();
+        fs::write(dir.path().join("untracked.txt"), "").unwrap();
+
+        let found = find_uncommitted_changes(&[
+            dir.path().join("clean.txt"),
+            dir.path().join("committed.txt"),
+            dir.path().join("untracked.txt"),
+        ]);
+        let found: Vec<PathBuf> = found.into_iter().map(|u| u.filepath).collect();
+        assert_eq!(
+            found,
+            vec![
+                dir.path().join("committed.txt"),
+                dir.path().join("untracked.txt"),
+            ]
+        );
+    }
+}
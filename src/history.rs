@@ -0,0 +1,209 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use tui_input::Input;
+
+use crate::config::state_dir;
+use crate::InputBox;
+use sr_rs::enums::search_enums::MatchEngine;
+use sr_rs::search::SearchOptions;
+
+/// Number of entries kept, the oldest ones are forgotten.
+const MAX_ENTRIES: usize = 500;
+
+/// The values of the input boxes for a search or replace.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub search: String,
+    pub replace: String,
+    pub glob: String,
+}
+
+impl HistoryEntry {
+    pub fn value(&self, input_box: &InputBox) -> &str {
+        match input_box {
+            InputBox::Search => &self.search,
+            InputBox::Replace => &self.replace,
+            InputBox::Filepath => &self.glob,
+        }
+    }
+}
+
+/// Previous searches of the user, oldest first, saved to a file after each
+/// change.
+#[derive(Debug, Default)]
+pub struct History {
+    /// Where the history is saved, kept in memory only without one
+    path: Option<PathBuf>,
+    entries: Vec<HistoryEntry>,
+}
+
+impl History {
+    /// Reads the history file, starting an empty history if it doesn't
+    /// exist yet.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let entries = match File::open(&path) {
+            Ok(file) => serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e),
+                )
+            })?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path: Some(path),
+            entries,
+        })
+    }
+
+    /// `history.json` in the state directory.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(state_dir()?.join("history.json"))
+    }
+
+    pub fn entries(&self) -> &[HistoryEntry] {
+        &self.entries
+    }
+
+    /// Adds the entry as the latest one, moving it there if it was already
+    /// in the history. Entries without a search are ignored.
+    pub fn record(&mut self, entry: HistoryEntry) {
+        if entry.search.is_empty() || self.entries.last() == Some(&entry) {
+            return;
+        }
+        self.entries.retain(|e| e != &entry);
+        self.entries.push(entry);
+        let overflow = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..overflow);
+
+        if let Some(path) = &self.path {
+            if let Err(e) = save(path, &self.entries) {
+                log::error!("Failed to save history to {}: {}", path.display(), e);
+            }
+        }
+    }
+
+    /// The distinct values of an input box, latest first.
+    pub fn values(&self, input_box: &InputBox) -> Vec<&str> {
+        let mut values: Vec<&str> = vec![];
+        for entry in self.entries.iter().rev() {
+            let value = entry.value(input_box);
+            if !value.is_empty() && !values.contains(&value) {
+                values.push(value);
+            }
+        }
+        values
+    }
+}
+
+fn save(path: &Path, entries: &[HistoryEntry]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let writer = BufWriter::new(File::create(path)?);
+    serde_json::to_writer(writer, entries).map_err(io::Error::other)
+}
+
+/// A popup listing the history entries fuzzy matching a query.
+#[derive(Debug, Default)]
+pub struct HistoryPicker {
+    pub query: Input,
+    /// Index of the selected entry among the matching ones
+    pub selected: usize,
+}
+
+impl HistoryPicker {
+    /// The entries matching the query in any of their values, latest first.
+    pub fn matches<'a>(&self, history: &'a History) -> Vec<&'a HistoryEntry> {
+        let options = SearchOptions {
+            engine: MatchEngine::Fuzzy,
+            ignore_case: true,
+            ..SearchOptions::default()
+        };
+        let Ok(matcher) = options.build_matcher(self.query.value()) else {
+            return vec![];
+        };
+        history
+            .entries()
+            .iter()
+            .rev()
+            .filter(|entry| {
+                self.query.value().is_empty()
+                    || [&entry.search, &entry.replace, &entry.glob]
+                        .iter()
+                        .any(|value| matcher.find_at(value, 0).is_some())
+            })
+            .collect()
+    }
+
+    pub fn select_next(&mut self, history: &History) {
+        if self.selected + 1 < self.matches(history).len() {
+            self.selected += 1;
+        }
+    }
+
+    pub fn select_prev(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// The selected entry, if any entry matches.
+    pub fn selected_entry<'a>(&self, history: &'a History) -> Option<&'a HistoryEntry> {
+        self.matches(history).get(self.selected).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(search: &str, replace: &str, glob: &str) -> HistoryEntry {
+        HistoryEntry {
+            search: search.into(),
+            replace: replace.into(),
+            glob: glob.into(),
+        }
+    }
+
+    #[test]
+    fn test_record_and_reload() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("sr/history.json");
+
+        let mut history = History::load(path.clone())?;
+        history.record(entry("foo", "bar", "*.rs"));
+        history.record(entry("baz", "", "*.rs"));
+        history.record(entry("foo", "bar", "*.rs"));
+        history.record(entry("", "ignored", ""));
+
+        let history = History::load(path)?;
+        assert_eq!(
+            history.entries(),
+            [entry("baz", "", "*.rs"), entry("foo", "bar", "*.rs")]
+        );
+        assert_eq!(history.values(&InputBox::Search), ["foo", "baz"]);
+        assert_eq!(history.values(&InputBox::Replace), ["bar"]);
+        assert_eq!(history.values(&InputBox::Filepath), ["*.rs"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_picker_matches() {
+        let mut history = History::default();
+        history.record(entry("match_struct", "matches", "src/**/*.rs"));
+        history.record(entry("TODO", "FIXME", "**/*"));
+
+        let mut picker = HistoryPicker::default();
+        assert_eq!(picker.matches(&history).len(), 2);
+        picker.query = Input::new("mtchs".into());
+        assert_eq!(
+            picker.selected_entry(&history),
+            Some(&entry("match_struct", "matches", "src/**/*.rs"))
+        );
+    }
+}
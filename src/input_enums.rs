@@ -4,6 +4,8 @@ pub enum InputMode {
     Normal,
     Editing,
     Refine,
+    /// Picking a previous search from the history
+    History,
 }
 
 /// Represents the different types of input boxes.
@@ -0,0 +1,541 @@
+use std::{collections::HashMap, fmt, io, str::FromStr};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+
+use crate::InputMode;
+
+/// Something a key does in the TUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    Quit,
+    StartEditing,
+    StopEditing,
+    StartRefining,
+    StopRefining,
+    NextInput,
+    PrevInput,
+    CycleEngine,
+    ToggleIgnoreCase,
+    ToggleWholeWord,
+    CycleTarget,
+    UndoRenames,
+    Replace,
+    ForceReplace,
+    NextRow,
+    PrevRow,
+    ToggleExcluded,
+    ToggleCollapsed,
+    OpenInEditor,
+    ReplaceMatch,
+    ReplaceAll,
+    HistoryPrev,
+    HistoryNext,
+    OpenHistory,
+    CloseHistory,
+    PickerDown,
+    PickerUp,
+    RunFromHistory,
+    SaveSession,
+}
+
+impl Action {
+    /// What the action does, as shown after its keys in the help panel.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::Quit => "to exit",
+            Self::StartEditing => "to start editing",
+            Self::StopEditing => "to stop editing",
+            Self::StartRefining => "to refine the matches",
+            Self::StopRefining => "to switch back to refine search",
+            Self::NextInput | Self::NextRow => "to go down",
+            Self::PrevInput | Self::PrevRow => "to go up",
+            Self::CycleEngine => "to cycle literal/regex/fuzzy",
+            Self::ToggleIgnoreCase => "to toggle ignore case",
+            Self::ToggleWholeWord => "to toggle whole word",
+            Self::CycleTarget => "to cycle contents/paths/both",
+            Self::UndoRenames => "to undo the last renames",
+            Self::Replace => "to replace all matches",
+            Self::ForceReplace => "to replace, even in uncommitted files",
+            Self::ToggleExcluded => "to exclude/include a match or file",
+            Self::ToggleCollapsed => "to collapse/expand a file",
+            Self::OpenInEditor => "to open a match in $EDITOR",
+            Self::ReplaceMatch => "to replace a match",
+            Self::ReplaceAll => "to replace all matches",
+            Self::HistoryPrev => "to recall an older value",
+            Self::HistoryNext => "to recall a newer value",
+            Self::OpenHistory => "to pick a previous search",
+            Self::CloseHistory => "to close the history",
+            Self::PickerDown => "to go down",
+            Self::PickerUp => "to go up",
+            Self::RunFromHistory => "to search again",
+            Self::SaveSession => "to save the session",
+        }
+    }
+
+    /// Whether the action can be bound in the mode.
+    fn is_available_in(&self, mode: &InputMode) -> bool {
+        match self {
+            Self::Quit | Self::SaveSession => matches!(mode, InputMode::Normal | InputMode::Refine),
+            Self::StopEditing | Self::HistoryPrev | Self::HistoryNext => {
+                matches!(mode, InputMode::Editing)
+            }
+            Self::StartEditing
+            | Self::StartRefining
+            | Self::NextInput
+            | Self::PrevInput
+            | Self::CycleEngine
+            | Self::ToggleIgnoreCase
+            | Self::ToggleWholeWord
+            | Self::CycleTarget
+            | Self::UndoRenames
+            | Self::Replace
+            | Self::ForceReplace
+            | Self::OpenHistory => matches!(mode, InputMode::Normal),
+            Self::StopRefining
+            | Self::NextRow
+            | Self::PrevRow
+            | Self::ToggleExcluded
+            | Self::ToggleCollapsed
+            | Self::OpenInEditor
+            | Self::ReplaceMatch
+            | Self::ReplaceAll => matches!(mode, InputMode::Refine),
+            Self::CloseHistory | Self::PickerDown | Self::PickerUp | Self::RunFromHistory => {
+                matches!(mode, InputMode::History)
+            }
+        }
+    }
+}
+
+/// A key with its modifiers, written `q`, `R`, `<Tab>` or `<C-r>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+/// Names of the keys written between angle brackets
+const KEY_NAMES: [(&str, KeyCode); 15] = [
+    ("Tab", KeyCode::Tab),
+    ("S-Tab", KeyCode::BackTab),
+    ("Esc", KeyCode::Esc),
+    ("Enter", KeyCode::Enter),
+    ("Space", KeyCode::Char(' ')),
+    ("BS", KeyCode::Backspace),
+    ("Del", KeyCode::Delete),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+];
+
+impl Key {
+    fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        Self { code, modifiers }
+    }
+
+    fn char(c: char) -> Self {
+        Self::new(KeyCode::Char(c), KeyModifiers::NONE)
+    }
+
+    fn named(code: KeyCode) -> Self {
+        Self::new(code, KeyModifiers::NONE)
+    }
+
+    /// Whether the key event is this key. Shift is part of the char typed,
+    /// so it is ignored for chars.
+    fn matches(&self, event: &KeyEvent) -> bool {
+        let mut modifiers = event.modifiers;
+        if matches!(event.code, KeyCode::Char(_)) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        event.code == self.code && modifiers == self.modifiers
+    }
+}
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            return Ok(Self::char(c));
+        }
+
+        let invalid = || format!("invalid key {:?}", s);
+        let mut name = s
+            .strip_prefix('<')
+            .and_then(|s| s.strip_suffix('>'))
+            .ok_or_else(invalid)?;
+
+        let mut modifiers = KeyModifiers::NONE;
+        loop {
+            let modifier = match name.get(..2).map(str::to_ascii_uppercase).as_deref() {
+                Some("C-") => KeyModifiers::CONTROL,
+                Some("A-") | Some("M-") => KeyModifiers::ALT,
+                _ => break,
+            };
+            modifiers.insert(modifier);
+            name = &name[2..];
+        }
+
+        let mut chars = name.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => KEY_NAMES
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, code)| *code)
+                .ok_or_else(invalid)?,
+        };
+        Ok(Self::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = KEY_NAMES.iter().find(|(_, code)| *code == self.code);
+        let name = match (name, self.code) {
+            (Some((name, _)), _) => name.to_string(),
+            (None, KeyCode::Char(c)) => c.to_string(),
+            (None, code) => format!("{:?}", code),
+        };
+        if self.modifiers.is_empty() {
+            return match name.chars().count() {
+                1 => write!(f, "{}", name),
+                _ => write!(f, "<{}>", name),
+            };
+        }
+        write!(f, "<")?;
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "C-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "A-")?;
+        }
+        write!(f, "{}>", name)
+    }
+}
+
+/// One key or several keys doing the same action, as written in the config.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum KeyList {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl KeyList {
+    fn keys(&self) -> Vec<&str> {
+        match self {
+            Self::One(key) => vec![key.as_str()],
+            Self::Many(keys) => keys.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// Keys rebound in the `[keys]` tables of the config, per mode:
+///
+/// ```toml
+/// [keys.normal]
+/// replace = ["r", "<C-r>"]
+///
+/// [keys.refine]
+/// toggle-excluded = "x"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct KeyBindings {
+    #[serde(default)]
+    pub normal: HashMap<Action, KeyList>,
+    #[serde(default)]
+    pub editing: HashMap<Action, KeyList>,
+    #[serde(default)]
+    pub refine: HashMap<Action, KeyList>,
+    #[serde(default)]
+    pub history: HashMap<Action, KeyList>,
+}
+
+impl KeyBindings {
+    /// The bindings with the ones of `over` replacing them.
+    pub fn merge(mut self, over: KeyBindings) -> KeyBindings {
+        self.normal.extend(over.normal);
+        self.editing.extend(over.editing);
+        self.refine.extend(over.refine);
+        self.history.extend(over.history);
+        self
+    }
+}
+
+/// The actions of the keys in each input mode, in the order they are listed
+/// in the help panel.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    normal: Vec<(Key, Action)>,
+    editing: Vec<(Key, Action)>,
+    refine: Vec<(Key, Action)>,
+    history: Vec<(Key, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use Action::*;
+        Self {
+            normal: vec![
+                (Key::char('q'), Quit),
+                (Key::char('i'), StartEditing),
+                (Key::named(KeyCode::Tab), StartRefining),
+                (Key::char('j'), NextInput),
+                (Key::char('k'), PrevInput),
+                (Key::char('x'), CycleEngine),
+                (Key::char('c'), ToggleIgnoreCase),
+                (Key::char('w'), ToggleWholeWord),
+                (Key::char('p'), CycleTarget),
+                (Key::char('u'), UndoRenames),
+                (Key::char('r'), Replace),
+                (Key::char('R'), ForceReplace),
+                (Key::char('h'), OpenHistory),
+                (Key::char('s'), SaveSession),
+            ],
+            editing: vec![
+                (Key::named(KeyCode::Esc), StopEditing),
+                (Key::named(KeyCode::Up), HistoryPrev),
+                (Key::named(KeyCode::Down), HistoryNext),
+            ],
+            refine: vec![
+                (Key::char('q'), Quit),
+                (Key::named(KeyCode::Tab), StopRefining),
+                (Key::char('j'), NextRow),
+                (Key::char('k'), PrevRow),
+                (Key::char('d'), ToggleExcluded),
+                (Key::char(' '), ToggleCollapsed),
+                (Key::named(KeyCode::Enter), ToggleCollapsed),
+                (Key::char('e'), OpenInEditor),
+                (Key::char('r'), ReplaceMatch),
+                (Key::char('a'), ReplaceAll),
+                (Key::char('s'), SaveSession),
+            ],
+            history: vec![
+                (Key::named(KeyCode::Esc), CloseHistory),
+                (Key::named(KeyCode::Enter), RunFromHistory),
+                (Key::named(KeyCode::Down), PickerDown),
+                (
+                    Key::new(KeyCode::Char('n'), KeyModifiers::CONTROL),
+                    PickerDown,
+                ),
+                (Key::named(KeyCode::Up), PickerUp),
+                (
+                    Key::new(KeyCode::Char('p'), KeyModifiers::CONTROL),
+                    PickerUp,
+                ),
+            ],
+        }
+    }
+}
+
+impl Keymap {
+    /// The default keymap with the keys rebound in the config. A key bound to
+    /// an action is taken away from any other action of the mode.
+    pub fn with_bindings(mut self, bindings: &KeyBindings) -> io::Result<Self> {
+        for (mode, rebound) in [
+            (InputMode::Normal, &bindings.normal),
+            (InputMode::Editing, &bindings.editing),
+            (InputMode::Refine, &bindings.refine),
+            (InputMode::History, &bindings.history),
+        ] {
+            for (action, keys) in rebound {
+                if !action.is_available_in(&mode) {
+                    return Err(invalid_binding(format!(
+                        "{:?} can't be bound in {} mode",
+                        action,
+                        mode_name(&mode)
+                    )));
+                }
+                let keys = keys
+                    .keys()
+                    .into_iter()
+                    .map(Key::from_str)
+                    .collect::<Result<Vec<Key>, String>>()
+                    .map_err(invalid_binding)?;
+                self.rebind(&mode, *action, &keys);
+            }
+        }
+        Ok(self)
+    }
+
+    fn rebind(&mut self, mode: &InputMode, action: Action, keys: &[Key]) {
+        let bindings = self.bindings_mut(mode);
+        let position = bindings
+            .iter()
+            .position(|(_, a)| *a == action)
+            .unwrap_or(bindings.len());
+        let position = position
+            - bindings[..position]
+                .iter()
+                .filter(|(key, a)| *a == action || keys.contains(key))
+                .count();
+        bindings.retain(|(key, a)| *a != action && !keys.contains(key));
+        for (i, key) in keys.iter().enumerate() {
+            bindings.insert(position + i, (*key, action));
+        }
+    }
+
+    /// The action of the key event in the mode, if it has one.
+    pub fn action(&self, mode: &InputMode, event: &KeyEvent) -> Option<Action> {
+        self.bindings(mode)
+            .iter()
+            .find(|(key, _)| key.matches(event))
+            .map(|(_, action)| *action)
+    }
+
+    /// The actions of the mode with their keys, joined with `/`, in order.
+    pub fn help(&self, mode: &InputMode) -> Vec<(String, Action)> {
+        let mut help: Vec<(String, Action)> = vec![];
+        for (key, action) in self.bindings(mode) {
+            match help.iter_mut().find(|(_, a)| a == action) {
+                Some((keys, _)) => *keys = format!("{}/{}", keys, key),
+                None => help.push((key.to_string(), *action)),
+            }
+        }
+        help
+    }
+
+    /// Lines the help panel needs to list the actions of any mode.
+    pub fn help_height(&self) -> usize {
+        [
+            InputMode::Normal,
+            InputMode::Editing,
+            InputMode::Refine,
+            InputMode::History,
+        ]
+        .iter()
+        .map(|mode| self.help(mode).len())
+        .max()
+        .unwrap_or_default()
+    }
+
+    fn bindings(&self, mode: &InputMode) -> &[(Key, Action)] {
+        match mode {
+            InputMode::Normal => &self.normal,
+            InputMode::Editing => &self.editing,
+            InputMode::Refine => &self.refine,
+            InputMode::History => &self.history,
+        }
+    }
+
+    fn bindings_mut(&mut self, mode: &InputMode) -> &mut Vec<(Key, Action)> {
+        match mode {
+            InputMode::Normal => &mut self.normal,
+            InputMode::Editing => &mut self.editing,
+            InputMode::Refine => &mut self.refine,
+            InputMode::History => &mut self.history,
+        }
+    }
+}
+
+fn mode_name(mode: &InputMode) -> &'static str {
+    match mode {
+        InputMode::Normal => "normal",
+        InputMode::Editing => "editing",
+        InputMode::Refine => "refine",
+        InputMode::History => "history",
+    }
+}
+
+fn invalid_binding(message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid key binding: {}", message),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_parse_and_display_keys() {
+        for key in [
+            "q",
+            "R",
+            "<Tab>",
+            "<Space>",
+            "<C-r>",
+            "<A-Enter>",
+            "<PageDown>",
+        ] {
+            assert_eq!(key.parse::<Key>().unwrap().to_string(), key);
+        }
+        assert_eq!(
+            "<c-R>".parse::<Key>().unwrap(),
+            Key::new(KeyCode::Char('R'), KeyModifiers::CONTROL)
+        );
+        assert!("<Nope>".parse::<Key>().is_err());
+        assert!("qq".parse::<Key>().is_err());
+    }
+
+    #[test]
+    fn test_shift_is_part_of_chars() {
+        let keymap = Keymap::default();
+        let event = press(KeyCode::Char('R'), KeyModifiers::SHIFT);
+        assert_eq!(
+            keymap.action(&InputMode::Normal, &event),
+            Some(Action::ForceReplace)
+        );
+    }
+
+    #[test]
+    fn test_rebind_keys() {
+        let bindings: KeyBindings = toml::from_str(
+            r#"
+            [normal]
+            replace = ["x", "<C-r>"]
+
+            [refine]
+            toggle-excluded = "x"
+            "#,
+        )
+        .unwrap();
+        let keymap = Keymap::default().with_bindings(&bindings).unwrap();
+
+        let normal = |code, modifiers| keymap.action(&InputMode::Normal, &press(code, modifiers));
+        assert_eq!(
+            normal(KeyCode::Char('x'), KeyModifiers::NONE),
+            Some(Action::Replace)
+        );
+        assert_eq!(
+            normal(KeyCode::Char('r'), KeyModifiers::CONTROL),
+            Some(Action::Replace)
+        );
+        assert_eq!(normal(KeyCode::Char('r'), KeyModifiers::NONE), None);
+
+        // The help lists the rebound keys where the action was
+        let help = keymap.help(&InputMode::Normal);
+        assert!(!help
+            .iter()
+            .any(|(_, action)| *action == Action::CycleEngine));
+        let replace = help
+            .iter()
+            .position(|(_, a)| *a == Action::Replace)
+            .unwrap();
+        assert_eq!(help[replace].0, "x/<C-r>");
+        assert_eq!(help[replace + 1].1, Action::ForceReplace);
+
+        let refine = keymap.help(&InputMode::Refine);
+        assert!(refine.contains(&("x".to_string(), Action::ToggleExcluded)));
+    }
+
+    #[test]
+    fn test_reject_actions_of_other_modes() {
+        let bindings: KeyBindings = toml::from_str("[refine]\nreplace = \"r\"").unwrap();
+        assert!(Keymap::default().with_bindings(&bindings).is_err());
+    }
+}
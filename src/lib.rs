@@ -0,0 +1,21 @@
+//! The search and replace engine behind sr, usable without the TUI.
+//!
+//! ```no_run
+//! use sr_rs::search::{replace_matches, search, ReplacePlan, SearchQuery};
+//!
+//! let query = SearchQuery::new("match_struct").glob("src/**/*.rs");
+//! let (matches, stats) = search(&query).expect("invalid pattern");
+//! println!("{} matches in {} files", matches.len(), stats.files_matched);
+//!
+//! let plan = ReplacePlan::new(matches).with_replacement("matches");
+//! replace_matches(&plan).expect("failed to replace");
+//! ```
+
+pub mod backup;
+pub mod dirs;
+pub mod editor;
+pub mod enums;
+pub mod git;
+pub mod models;
+pub mod output;
+pub mod search;
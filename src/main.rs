@@ -1,23 +1,48 @@
-use crossterm::event::{self, Event, KeyCode};
-use ratatui::{backend::Backend, Terminal};
-use search::replace;
-use std::{error::Error, io};
+use clap::Parser;
+use crossterm::event::{self, Event, KeyEvent};
+use sr_rs::editor::open_in_editor;
+use sr_rs::models::match_struct::Match;
+use sr_rs::search::{
+    filter, list_files, read_rg_json, search, search_rules, RuleSet, SearchQuery, SearchStats,
+};
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufReader, Write},
+    time::Duration,
+};
 use tui_input::backend::crossterm::EventHandler;
 
 use app::App;
-use enums::input_enums::{InputBox, InputMode};
+use cli::Cli;
+use config::Config;
+use history::{History, HistoryPicker};
+use input_enums::{InputBox, InputMode};
+use keymap::{Action, Keymap};
 use logging::init_logger;
-use tui::{restore_terminal, setup_terminal};
+use session::Session;
+use sr_rs::enums::search_enums::MatchSource;
+use sr_rs::output::{write_json, write_quickfix};
+use theme::Theme;
+use tui::{restore_terminal, setup_terminal, Tui};
 use ui::ui as user_interface;
 
 mod app;
-mod enums;
+mod cli;
+mod config;
+mod file_group;
+mod history;
+mod input_enums;
+mod keymap;
 mod logging;
-mod models;
-mod search;
+mod session;
+mod theme;
 mod tui;
 mod ui;
 
+/// How often file changes are picked up in watch mode
+const WATCH_INTERVAL: Duration = Duration::from_millis(250);
+
 fn main() {
     if let Err(e) = run() {
         eprintln!("Application error: {}", e);
@@ -27,6 +52,20 @@ fn main() {
 
 /// Main function that starts the application
 fn run() -> Result<(), Box<dyn Error>> {
+    let mut cli = Cli::parse();
+    let config = match cli.no_config {
+        true => Config::default(),
+        false => Config::discover()?,
+    };
+    // Flags take precedence over the preset, which takes precedence over the
+    // config's defaults
+    let settings = config.settings(cli.preset.as_deref())?;
+    let search_options = cli.search_options(settings.search_options());
+    cli.search = cli.search.or(settings.search);
+    cli.replace = cli.replace.or(settings.replace);
+    cli.glob = cli.glob.or(settings.glob);
+    let context_lines = cli.context.or(settings.context_lines).unwrap_or_default();
+    let git_filter = cli.git_filter();
     init_logger()?;
     let sr_logging_art = "
 ######################
@@ -42,11 +81,103 @@ fn run() -> Result<(), Box<dyn Error>> {
     log::info!("sr is starting up");
     log::info!("{}", sr_logging_art);
 
-    // setup terminal
-    let mut terminal = setup_terminal()?;
+    if cli.filter {
+        filter(
+            &mut io::stdin().lock(),
+            &mut io::stdout().lock(),
+            &cli.search.unwrap_or_default(),
+            &cli.replace.unwrap_or_default(),
+            &search_options,
+        )?;
+        return Ok(());
+    }
+
+    if let Some(id) = &cli.restore {
+        let filepaths = list_files(cli.glob.as_deref().unwrap_or_default());
+        for restored in cli.backups().restore(id.as_deref(), &filepaths)? {
+            println!("restored {}", restored.display());
+        }
+        return Ok(());
+    }
+
+    let imported = match &cli.rg_json {
+        Some(path) if path.as_os_str() == "-" => Some(read_rg_json(io::stdin().lock())?),
+        Some(path) => Some(read_rg_json(BufReader::new(File::open(path)?))?),
+        None => None,
+    };
+
+    let rules = cli.rules.as_deref().map(RuleSet::load).transpose()?;
+
+    if cli.format.is_some() || cli.json {
+        let mut writer: Box<dyn Write> = match &cli.output {
+            Some(path) => Box::new(File::create(path)?),
+            None => Box::new(io::stdout().lock()),
+        };
+        let search_pattern = cli.search.unwrap_or_default();
+        let search_glob = cli.glob.unwrap_or_default();
+        let (matches, stats) = match (imported, rules) {
+            (Some(matches), _) => (
+                with_replacement(matches, &cli.replace),
+                SearchStats::default(),
+            ),
+            (None, Some(rules)) => {
+                let (matches, stats, overlaps) = search_rules(&rules)?;
+                for overlap in &overlaps {
+                    eprintln!("warning: {}", overlap);
+                }
+                // Overlapping matches aren't replaced, so don't report them
+                let matches = matches.into_iter().filter(|m| !m.is_excluded()).collect();
+                (matches, stats)
+            }
+            (None, None) => {
+                let (matches, stats) = search(
+                    &SearchQuery::new(search_pattern.clone())
+                        .glob(search_glob.clone())
+                        .options(search_options)
+                        .git(git_filter),
+                )?;
+                (with_replacement(matches, &cli.replace), stats)
+            }
+        };
+        match cli.format {
+            Some(format) => write_quickfix(&mut writer, &matches, format)?,
+            None => write_json(&mut writer, &search_pattern, &search_glob, &matches, &stats)?,
+        }
+        return Ok(());
+    }
+
+    // create app, pre-filled from the command line
+    let mut app = App {
+        search_options,
+        git: git_filter,
+        require_committed: cli.git_committed,
+        backups: cli.backup.is_some().then(|| cli.backups()),
+        context_lines,
+        keymap: Keymap::default().with_bindings(&config.keys)?,
+        history: load_history(),
+        session_path: cli.session.clone(),
+        theme: Theme::resolve(
+            cli.theme.as_deref().or(config.theme.as_deref()),
+            &config.themes,
+        )?,
+        ..App::default()
+    };
+    app.set_input(InputBox::Search, cli.search.unwrap_or_default());
+    app.set_input(InputBox::Replace, cli.replace.unwrap_or_default());
+    app.set_input(InputBox::Filepath, cli.glob.unwrap_or_default());
+    match (imported, rules, &cli.session) {
+        (Some(matches), _, _) => app.import_matches(matches),
+        (None, Some(rules), _) => app.load_rules(rules),
+        // A new session starts from the flags and config
+        (None, None, Some(path)) if path.exists() => app.restore_session(Session::load(path)?),
+        (None, None, _) => app.refresh_matches(),
+    }
+    if cli.watch {
+        app.watch();
+    }
 
-    // create app and run it
-    let app = App::default();
+    // setup terminal and run the app
+    let mut terminal = setup_terminal()?;
     let res = run_app(&mut terminal, app);
 
     restore_terminal()?;
@@ -59,71 +190,170 @@ fn run() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<()> {
+/// The user's history of searches. A history that can't be read is logged
+/// and replaced with an empty one, kept in memory.
+fn load_history() -> History {
+    let Some(path) = History::default_path() else {
+        return History::default();
+    };
+    History::load(path).unwrap_or_else(|e| {
+        log::error!("Failed to read history: {}", e);
+        History::default()
+    })
+}
+
+/// Sets the replacement of every match from the `--replace` template.
+fn with_replacement(mut matches: Vec<Match>, replace: &Option<String>) -> Vec<Match> {
+    for m in matches.iter_mut() {
+        m.set_replacement_template(replace.as_deref().unwrap_or_default());
+    }
+    matches
+}
+
+fn run_app(terminal: &mut Tui, mut app: App) -> io::Result<()> {
     loop {
-        terminal.draw(|f| user_interface::<B>(f, &app))?;
+        app.poll_file_changes();
+        terminal.draw(|f| user_interface(f, &app))?;
+
+        // Wake up regularly to pick up file changes while watching
+        if app.watcher.is_some() && !event::poll(WATCH_INTERVAL)? {
+            continue;
+        }
 
-        if let Event::Key(key) = event::read()? {
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        let Some(action) = app.keymap.action(&app.input_mode, &key) else {
+            // Other keys edit the selected input box or the picker's query
             match app.input_mode {
-                InputMode::Normal => match key.code {
-                    KeyCode::Char('i') => {
-                        app.input_mode = InputMode::Editing;
-                    }
-                    KeyCode::Tab => {
-                        app.input_mode = InputMode::Refine;
-                    }
-                    KeyCode::Char('q') => {
-                        return Ok(());
-                    }
-                    KeyCode::Char('j') => {
-                        app.input_box_selection = app.input_box_selection.next();
-                    }
-                    KeyCode::Char('k') => {
-                        app.input_box_selection = app.input_box_selection.prev();
-                    }
-                    KeyCode::Char('r') => match replace(&app) {
-                        Err(e) => {
-                            log::error!("Failed to replace: {}", e);
-                        }
-                        Ok(_) => {
-                            log::info!("Successfully replaced matches");
-                        }
-                    },
-                    _ => {}
-                },
-                InputMode::Refine => match key.code {
-                    KeyCode::Tab => {
-                        app.input_mode = InputMode::Normal;
-                    }
-                    KeyCode::Char('q') => {
-                        return Ok(());
-                    }
-                    KeyCode::Char('j') => {
-                        log::info!("go to next match");
-                    }
-                    KeyCode::Char('k') => {
-                        log::info!("go to previous match");
-                    }
-                    KeyCode::Char('d') => {
-                        log::info!("delete match");
+                InputMode::Editing => edit_input(&mut app, key),
+                InputMode::History => {
+                    app.history_picker.query.handle_event(&Event::Key(key));
+                    app.history_picker.selected = 0;
+                }
+                InputMode::Normal | InputMode::Refine => {}
+            }
+            continue;
+        };
+        match action {
+            Action::Quit => {
+                // Sessions opened with `--session` are saved on exit
+                if app.session_path.is_some() {
+                    app.save_session()?;
+                }
+                return Ok(());
+            }
+            Action::StartEditing => app.input_mode = InputMode::Editing,
+            Action::StopEditing => {
+                app.record_history();
+                app.history_position = None;
+                app.input_mode = InputMode::Normal;
+            }
+            Action::StopRefining | Action::CloseHistory => app.input_mode = InputMode::Normal,
+            Action::StartRefining => app.input_mode = InputMode::Refine,
+            Action::NextInput => app.input_box_selection = app.input_box_selection.next(),
+            Action::PrevInput => app.input_box_selection = app.input_box_selection.prev(),
+            Action::CycleEngine => {
+                app.search_options.engine = app.search_options.engine.next();
+                app.refresh_matches();
+            }
+            Action::ToggleIgnoreCase => {
+                app.search_options.ignore_case = !app.search_options.ignore_case;
+                app.refresh_matches();
+            }
+            Action::ToggleWholeWord => {
+                app.search_options.whole_word = !app.search_options.whole_word;
+                app.refresh_matches();
+            }
+            Action::CycleTarget => {
+                if app.match_source == MatchSource::Search {
+                    app.replace_target = app.replace_target.next();
+                    app.refresh_matches();
+                }
+            }
+            Action::UndoRenames => {
+                match app.undo_renames() {
+                    Err(e) => {
+                        log::error!("Failed to undo renames: {}", e);
+                        app.status_message = Some(format!("Failed to undo: {}", e));
                     }
-                    KeyCode::Char('r') => {
-                        log::info!("replace match");
+                    Ok(0) => app.status_message = Some("Nothing to undo".into()),
+                    Ok(count) => app.status_message = Some(format!("Moved back {} paths", count)),
+                }
+                app.refresh_matches();
+            }
+            Action::Replace | Action::ForceReplace => {
+                app.record_history();
+                match app.replace(action == Action::ForceReplace) {
+                    Err(e) => {
+                        log::error!("Failed to replace: {}", e);
+                        app.status_message = Some(format!("Failed to replace: {}", e));
                     }
-                    KeyCode::Char('a') => {
-                        log::info!("replace all matches");
+                    Ok(0) => {
+                        log::info!("Successfully replaced matches");
+                        app.status_message = Some("Replaced matches".into());
                     }
-                    _ => {}
-                },
-                InputMode::Editing => match key.code {
-                    KeyCode::Esc => {
-                        app.input_mode = InputMode::Normal;
+                    Ok(skipped) => {
+                        log::info!("Replaced matches, skipped {} conflicts", skipped);
+                        app.status_message =
+                            Some(format!("Replaced matches, skipped {} overlapping", skipped));
                     }
-                    _ => {
-                        app.input[app.input_box_selection.pos()].handle_event(&Event::Key(key));
+                }
+                match app.match_source {
+                    MatchSource::Search | MatchSource::Rules => app.refresh_matches(),
+                    // Offsets of imported matches are stale once the files change
+                    MatchSource::Ripgrep => app.import_matches(vec![]),
+                }
+            }
+            Action::NextRow => app.select_next_row(),
+            Action::PrevRow => app.select_prev_row(),
+            Action::ToggleExcluded => app.toggle_selected_excluded(),
+            Action::ToggleCollapsed => app.toggle_selected_collapsed(),
+            Action::OpenInEditor => {
+                if let Some(m) = app.selected_match().cloned() {
+                    // Hand the terminal over to the editor while it runs
+                    restore_terminal()?;
+                    if let Err(e) = open_in_editor(&m) {
+                        log::error!("Failed to open editor: {}", e);
                     }
-                },
+                    *terminal = setup_terminal()?;
+                    terminal.clear()?;
+                    app.refresh_matches();
+                }
+            }
+            Action::ReplaceMatch => log::info!("replace match"),
+            Action::ReplaceAll => log::info!("replace all matches"),
+            Action::HistoryPrev => app.recall_history(true),
+            Action::HistoryNext => app.recall_history(false),
+            Action::OpenHistory => {
+                app.history_picker = HistoryPicker::default();
+                app.input_mode = InputMode::History;
+            }
+            Action::PickerDown => app.history_picker.select_next(&app.history),
+            Action::PickerUp => app.history_picker.select_prev(),
+            Action::RunFromHistory => {
+                app.run_from_history();
+                app.input_mode = InputMode::Normal;
+            }
+            Action::SaveSession => {
+                app.status_message = Some(match app.save_session() {
+                    Ok(path) => format!("Session saved to {}", path.display()),
+                    Err(e) => format!("Failed to save session: {}", e),
+                });
             }
         }
     }
 }
+
+/// Passes a key to the selected input box, searching again if its value
+/// changed.
+fn edit_input(app: &mut App, key: KeyEvent) {
+    let changed = app.input[app.input_box_selection.pos()]
+        .handle_event(&Event::Key(key))
+        .is_some_and(|state| state.value);
+    if changed {
+        // Typing starts over from the latest history entry
+        app.history_position = None;
+        app.input_changed();
+    }
+}
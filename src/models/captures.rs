@@ -0,0 +1,105 @@
+/// The capture groups of a match, used to expand `$1`/`${name}` references
+/// in a replacement. Group 0 is the whole match.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Captures {
+    groups: Vec<Option<String>>,
+    names: Vec<Option<String>>,
+}
+
+impl Captures {
+    /// Builds the captures from each group's text and name, in group order.
+    pub fn new(groups: Vec<Option<String>>, names: Vec<Option<String>>) -> Self {
+        Self { groups, names }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Text of the group with this index or name, if it took part in the match.
+    pub fn get(&self, group: &str) -> Option<&str> {
+        let index = match group.parse::<usize>() {
+            Ok(index) => index,
+            Err(_) => self
+                .names
+                .iter()
+                .position(|name| name.as_deref() == Some(group))?,
+        };
+        self.groups.get(index)?.as_deref()
+    }
+
+    /// Expands `$N`, `${N}`, `$name` and `${name}` in the template with the
+    /// text of the groups, and `$$` to a literal `$`. Unknown groups expand to
+    /// nothing. Without any groups the template is returned as is.
+    pub fn expand(&self, template: &str) -> String {
+        if self.is_empty() {
+            return template.to_string();
+        }
+
+        let mut expanded = String::with_capacity(template.len());
+        let mut rest = template;
+        while let Some(dollar) = rest.find('$') {
+            expanded.push_str(&rest[..dollar]);
+            rest = &rest[dollar + 1..];
+
+            if let Some(after) = rest.strip_prefix('$') {
+                expanded.push('$');
+                rest = after;
+                continue;
+            }
+
+            let (group, after) = match rest.strip_prefix('{') {
+                Some(braced) => match braced.find('}') {
+                    Some(end) => (&braced[..end], &braced[end + 1..]),
+                    None => ("", rest),
+                },
+                None => {
+                    let end = rest
+                        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                        .unwrap_or(rest.len());
+                    (&rest[..end], &rest[end..])
+                }
+            };
+            if group.is_empty() {
+                // Not a group reference, keep the `$`
+                expanded.push('$');
+                continue;
+            }
+            expanded.push_str(self.get(group).unwrap_or(""));
+            rest = after;
+        }
+        expanded.push_str(rest);
+        expanded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn captures() -> Captures {
+        Captures::new(
+            vec![
+                Some("John Smith".into()),
+                Some("John".into()),
+                Some("Smith".into()),
+                None,
+            ],
+            vec![None, Some("first".into()), Some("last".into()), None],
+        )
+    }
+
+    #[test]
+    fn test_expand() {
+        let captures = captures();
+        assert_eq!(captures.expand("$2, $1"), "Smith, John");
+        assert_eq!(captures.expand("${last}_x $first"), "Smith_x John");
+        assert_eq!(captures.expand("[$0] $3 $9"), "[John Smith]  ");
+        assert_eq!(captures.expand("$$1 costs $"), "$1 costs $");
+    }
+
+    #[test]
+    fn test_expand_without_groups() {
+        assert_eq!(Captures::default().expand("$1 ${x}"), "$1 ${x}");
+    }
+}
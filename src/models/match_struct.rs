@@ -1,9 +1,6 @@
 use std::fmt;
 
-use ratatui::{
-    style::Style,
-    text::{Line, Span},
-};
+use super::captures::Captures;
 
 #[derive(Debug, Clone)]
 pub struct Match {
@@ -15,6 +12,11 @@ pub struct Match {
     replacement: String,
     line: String,
     line_num: usize,
+    excluded: bool,
+    captures: Captures,
+    rule: Option<usize>,
+    stale: bool,
+    text: String,
 }
 
 impl fmt::Display for Match {
@@ -28,6 +30,7 @@ impl fmt::Display for Match {
 }
 
 impl Match {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         filepath: String,
         file_index_start: usize,
@@ -47,29 +50,36 @@ impl Match {
             replacement,
             line,
             line_num,
+            excluded: false,
+            captures: Captures::default(),
+            rule: None,
+            stale: false,
+            text: String::new(),
         }
     }
-    pub fn tui_fmt(&self) -> Vec<Line> {
-        let (start_byte_index, end_byte_index) = self.get_byte_indices();
 
-        let spans = vec![
-            Span::raw(format!("line: {} \t", self.line_num)),
-            Span::raw(&self.line[..start_byte_index]),
-            Span::styled(
-                &self.line[start_byte_index..end_byte_index],
-                Style::default().fg(ratatui::style::Color::Red),
-            ),
-            Span::styled(
-                &self.replacement,
-                Style::default().fg(ratatui::style::Color::Green),
-            ),
-            Span::raw(&self.line[end_byte_index..]),
-        ];
+    /// Attaches the capture groups of the match, which replacement templates
+    /// can refer to.
+    pub fn with_captures(mut self, captures: Captures) -> Self {
+        self.captures = captures;
+        self
+    }
+
+    /// Records the text the match covered when the file was searched, which is
+    /// checked again before replacing it.
+    pub fn with_text(mut self, text: String) -> Self {
+        self.text = text;
+        self
+    }
 
-        vec![Span::raw(&self.filepath).into(), spans.into()]
+    /// Records the index of the rule in a rules file that found the match.
+    pub fn with_rule(mut self, rule: usize) -> Self {
+        self.rule = Some(rule);
+        self
     }
 
-    fn get_byte_indices(&self) -> (usize, usize) {
+    /// Byte offsets of the start and end of the match within its line.
+    pub fn get_byte_indices(&self) -> (usize, usize) {
         let start_byte_index = self
             .line
             .char_indices()
@@ -90,6 +100,35 @@ impl Match {
         self.replacement = replacement;
     }
 
+    /// Sets the replacement from a template, expanding references to the
+    /// match's capture groups such as `$1` or `${name}`.
+    pub fn set_replacement_template(&mut self, template: &str) {
+        self.replacement = self.captures.expand(template);
+    }
+
+    /// Marks the match as excluded from (or included in) the replace list.
+    pub fn set_excluded(&mut self, excluded: bool) {
+        self.excluded = excluded;
+    }
+
+    pub fn is_excluded(&self) -> bool {
+        self.excluded
+    }
+
+    /// Marks the match as invalidated by a change to its file on disk. Stale
+    /// matches are never replaced.
+    pub fn set_stale(&mut self, stale: bool) {
+        self.stale = stale;
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+
+    pub fn get_rule(&self) -> Option<usize> {
+        self.rule
+    }
+
     pub fn get_filepath(&self) -> &str {
         &self.filepath
     }
@@ -102,6 +141,23 @@ impl Match {
     pub fn get_match_length(&self) -> usize {
         self.match_length
     }
+    pub fn get_start_on_line(&self) -> usize {
+        self.start_on_line
+    }
+    pub fn get_line_num(&self) -> usize {
+        self.line_num
+    }
+    pub fn get_line(&self) -> &str {
+        &self.line
+    }
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+
+    /// Byte offset of the start of the match within its line.
+    pub fn get_byte_start_on_line(&self) -> usize {
+        self.get_byte_indices().0
+    }
 }
 
 #[cfg(test)]
@@ -119,6 +175,11 @@ mod tests {
             start_on_line: 7,
             end_on_line: 8,
             replacement: String::from(""),
+            excluded: false,
+            captures: Captures::default(),
+            rule: None,
+            stale: false,
+            text: String::new(),
         };
 
         let (byte_start, byte_end) = m.get_byte_indices();
@@ -0,0 +1,136 @@
+use std::io::{self, Write};
+
+use serde::{Deserialize, Serialize};
+
+use crate::models::match_struct::Match;
+use crate::search::SearchStats;
+
+/// The stable, serialized form of a [`Match`]. Lines and columns are 1-based,
+/// byte offsets and lengths are 0-based and counted in bytes.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MatchRecord {
+    pub path: String,
+    pub line_number: usize,
+    /// Column of the match on its line, counted in chars
+    pub column: usize,
+    /// Offset of the match from the start of the file
+    pub byte_offset: usize,
+    pub length: usize,
+    /// The whole line the match is on, without its newline
+    pub line_text: String,
+    pub replacement: String,
+}
+
+impl From<&Match> for MatchRecord {
+    fn from(m: &Match) -> Self {
+        Self {
+            path: m.get_filepath().to_string(),
+            line_number: m.get_line_num() + 1,
+            column: m.get_start_on_line() + 1,
+            byte_offset: m.get_file_index_start(),
+            length: m.get_match_length(),
+            line_text: m.get_line().to_string(),
+            replacement: m.get_replacement().to_string(),
+        }
+    }
+}
+
+/// A single line of JSON output.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum Message {
+    Begin {
+        pattern: String,
+        glob: String,
+    },
+    Match(MatchRecord),
+    End {
+        matches: usize,
+        files_matched: usize,
+        files_scanned: usize,
+        files_skipped: usize,
+        elapsed_ms: u128,
+    },
+}
+
+/// Writes the matches as JSON Lines: a `begin` record, one `match` record per
+/// match and an `end` record summarizing the search.
+pub fn write_json<W: Write>(
+    writer: &mut W,
+    pattern: &str,
+    glob: &str,
+    matches: &[Match],
+    stats: &SearchStats,
+) -> io::Result<()> {
+    let begin = Message::Begin {
+        pattern: pattern.into(),
+        glob: glob.into(),
+    };
+    write_message(writer, &begin)?;
+    for m in matches {
+        write_message(writer, &Message::Match(m.into()))?;
+    }
+    let end = Message::End {
+        matches: matches.len(),
+        files_matched: stats.files_matched,
+        files_scanned: stats.files_scanned,
+        files_skipped: stats.files_skipped,
+        elapsed_ms: stats.elapsed.as_millis(),
+    };
+    write_message(writer, &end)?;
+    writer.flush()
+}
+
+fn write_message<W: Write>(writer: &mut W, message: &Message) -> io::Result<()> {
+    serde_json::to_writer(&mut *writer, message)?;
+    writeln!(writer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_json() {
+        let mut m = Match::new(
+            "src/test.txt".into(),
+            13,
+            5,
+            3,
+            8,
+            "".into(),
+            "世界 world".into(),
+            1,
+        );
+        m.set_replacement("Rust".into());
+        let stats = SearchStats {
+            files_scanned: 2,
+            files_matched: 1,
+            ..SearchStats::default()
+        };
+
+        let mut out = vec![];
+        write_json(&mut out, "world", "src/*", &[m], &stats).unwrap();
+        let lines: Vec<&str> = std::str::from_utf8(&out).unwrap().lines().collect();
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(
+            lines[0],
+            r#"{"type":"begin","data":{"pattern":"world","glob":"src/*"}}"#
+        );
+        assert_eq!(
+            lines[1],
+            r#"{"type":"match","data":{"path":"src/test.txt","line_number":2,"column":4,"byte_offset":13,"length":5,"line_text":"世界 world","replacement":"Rust"}}"#
+        );
+        assert_eq!(
+            lines[2],
+            r#"{"type":"end","data":{"matches":1,"files_matched":1,"files_scanned":2,"files_skipped":0,"elapsed_ms":0}}"#
+        );
+
+        // Every line round-trips
+        for line in lines {
+            let message: Message = serde_json::from_str(line).unwrap();
+            assert_eq!(serde_json::to_string(&message).unwrap(), line);
+        }
+    }
+}
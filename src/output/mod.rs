@@ -0,0 +1,4 @@
+pub mod json;
+pub mod quickfix;
+pub use json::write_json;
+pub use quickfix::write_quickfix;
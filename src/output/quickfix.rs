@@ -0,0 +1,74 @@
+use std::io::{self, Write};
+
+use crate::enums::output_enums::OutputFormat;
+use crate::models::match_struct::Match;
+
+/// Writes one line per match in a format editors can jump through. Lines and
+/// columns are 1-based; Vim counts columns in bytes and Emacs in chars.
+pub fn write_quickfix<W: Write>(
+    writer: &mut W,
+    matches: &[Match],
+    format: OutputFormat,
+) -> io::Result<()> {
+    for m in matches {
+        let line = m.get_line_num() + 1;
+        match format {
+            OutputFormat::Quickfix => writeln!(
+                writer,
+                "{}:{}:{}:{}",
+                m.get_filepath(),
+                line,
+                m.get_byte_start_on_line() + 1,
+                m.get_line()
+            )?,
+            OutputFormat::Emacs => writeln!(
+                writer,
+                "{}:{}:{}: {}",
+                m.get_filepath(),
+                line,
+                m.get_start_on_line() + 1,
+                m.get_line()
+            )?,
+        }
+    }
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches() -> Vec<Match> {
+        // "world" on the second line of "first\n世界 world"
+        vec![Match::new(
+            "src/test.txt".into(),
+            13,
+            5,
+            3,
+            8,
+            "".into(),
+            "世界 world".into(),
+            1,
+        )]
+    }
+
+    #[test]
+    fn test_write_quickfix() {
+        let mut out = vec![];
+        write_quickfix(&mut out, &matches(), OutputFormat::Quickfix).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "src/test.txt:2:8:世界 world\n"
+        );
+    }
+
+    #[test]
+    fn test_write_emacs() {
+        let mut out = vec![];
+        write_quickfix(&mut out, &matches(), OutputFormat::Emacs).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "src/test.txt:2:4: 世界 world\n"
+        );
+    }
+}
@@ -0,0 +1,112 @@
+use std::{
+    fs::File,
+    io::{self, Read},
+    ops::Deref,
+    path::Path,
+};
+
+use memmap2::Mmap;
+
+/// Files at least this large are memory mapped instead of read into memory
+pub const MMAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// The text of a file, either read into memory or memory mapped.
+pub enum FileContents {
+    Read(String),
+    /// Mapped contents, checked to be valid UTF-8 when mapping
+    Mapped(Mmap),
+}
+
+impl Deref for FileContents {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        match self {
+            Self::Read(contents) => contents,
+            // SAFETY: the mapped bytes were checked to be valid UTF-8 in
+            // `read_with_threshold`
+            Self::Mapped(mmap) => unsafe { std::str::from_utf8_unchecked(mmap) },
+        }
+    }
+}
+
+impl FileContents {
+    /// Reads the text of a file, memory mapping files above
+    /// [`MMAP_THRESHOLD`]. Fails for binary files and files that aren't valid
+    /// UTF-8.
+    pub fn read(file_path: &Path) -> io::Result<Self> {
+        read_with_threshold(file_path, MMAP_THRESHOLD)
+    }
+}
+
+fn read_with_threshold(file_path: &Path, mmap_threshold: u64) -> io::Result<FileContents> {
+    let mut file = File::open(file_path)?;
+    let len = file.metadata()?.len();
+    if len < mmap_threshold {
+        let mut bytes = Vec::with_capacity(len as usize);
+        file.read_to_end(&mut bytes)?;
+        check_text(file_path, &bytes)?;
+        let contents =
+            String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        return Ok(FileContents::Read(contents));
+    }
+
+    // SAFETY: the map is only read from. If another process truncates the
+    // file while it's mapped, reads past the new end fault, the same risk
+    // every mmap based search tool takes.
+    let mmap = unsafe { Mmap::map(&file)? };
+    check_text(file_path, &mmap)?;
+    std::str::from_utf8(&mmap).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    Ok(FileContents::Mapped(mmap))
+}
+
+/// Treats files containing a NUL byte as binary, the same heuristic grep uses
+fn check_text(file_path: &Path, bytes: &[u8]) -> io::Result<()> {
+    if bytes.contains(&0) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} is a binary file", file_path.display()),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_read_small_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, "Hello, 世界!").unwrap();
+
+        let contents = FileContents::read(&file_path).unwrap();
+        assert!(matches!(contents, FileContents::Read(_)));
+        assert_eq!(&*contents, "Hello, 世界!");
+    }
+
+    #[test]
+    fn test_map_large_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("test.txt");
+        fs::write(&file_path, "Hello, 世界!").unwrap();
+
+        let contents = read_with_threshold(&file_path, 0).unwrap();
+        assert!(matches!(contents, FileContents::Mapped(_)));
+        assert_eq!(&*contents, "Hello, 世界!");
+    }
+
+    #[test]
+    fn test_mapped_file_must_be_text() {
+        let dir = tempfile::tempdir().unwrap();
+        let binary_path = dir.path().join("test.bin");
+        let latin1_path = dir.path().join("latin1.txt");
+        fs::write(&binary_path, b"Hello\0world").unwrap();
+        fs::write(&latin1_path, b"caf\xe9").unwrap();
+
+        assert!(read_with_threshold(&binary_path, 0).is_err());
+        assert!(read_with_threshold(&latin1_path, 0).is_err());
+    }
+}
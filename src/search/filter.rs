@@ -0,0 +1,112 @@
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use super::options::SearchOptions;
+use super::replace::apply_matches;
+use super::search::find_matches_in_file;
+
+/// Name matches found in stdin are given in place of a file path
+const STDIN_PATH: &str = "-";
+
+/// Runs sr as a filter: reads all of `input`, replaces every match of the
+/// pattern and writes the result to `output`, like `sed -e s///g`.
+pub fn filter<R: Read, W: Write>(
+    input: &mut R,
+    output: &mut W,
+    search_pattern: &str,
+    replacement: &str,
+    options: &SearchOptions,
+) -> io::Result<()> {
+    let mut contents = String::new();
+    input.read_to_string(&mut contents)?;
+
+    let new_contents = if search_pattern.is_empty() {
+        contents
+    } else {
+        let matcher = options
+            .build_matcher(search_pattern)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let mut matches = find_matches_in_file(&contents, matcher.as_ref(), Path::new(STDIN_PATH));
+        for m in matches.iter_mut() {
+            m.set_replacement_template(replacement);
+        }
+        apply_matches(contents, matches)
+    };
+
+    output.write_all(new_contents.as_bytes())?;
+    output.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::search_enums::MatchEngine;
+
+    fn run_filter(input: &str, pattern: &str, replacement: &str, options: SearchOptions) -> String {
+        let mut output = vec![];
+        filter(
+            &mut input.as_bytes(),
+            &mut output,
+            pattern,
+            replacement,
+            &options,
+        )
+        .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_filter_literal() {
+        let output = run_filter("a.b axb\na.b\n", "a.b", "c", SearchOptions::default());
+        assert_eq!(output, "c axb\nc\n");
+    }
+
+    #[test]
+    fn test_filter_regex_ignore_case_whole_word() {
+        let options = SearchOptions {
+            engine: MatchEngine::Regex,
+            ignore_case: true,
+            whole_word: true,
+        };
+        let output = run_filter("Cat cats CAT\n", "ca.", "dog", options);
+        assert_eq!(output, "dog cats dog\n");
+    }
+
+    #[test]
+    fn test_filter_regex_captures() {
+        let options = SearchOptions {
+            engine: MatchEngine::Regex,
+            ..SearchOptions::default()
+        };
+        let output = run_filter("John Smith\n", r"(\w+) (\w+)", "$2, $1", options);
+        assert_eq!(output, "Smith, John\n");
+    }
+
+    #[test]
+    fn test_filter_regex_anchors_to_lines() {
+        let options = SearchOptions {
+            engine: MatchEngine::Regex,
+            ..SearchOptions::default()
+        };
+        let output = run_filter("foo\nfoo\nx foo\n", "^foo$", "bar", options);
+        assert_eq!(output, "bar\nbar\nx foo\n");
+    }
+
+    #[test]
+    fn test_filter_literal_replacement_keeps_dollars() {
+        let output = run_filter("price\n", "price", "$1", SearchOptions::default());
+        assert_eq!(output, "$1\n");
+    }
+
+    #[test]
+    fn test_filter_invalid_regex() {
+        let options = SearchOptions {
+            engine: MatchEngine::Regex,
+            ..SearchOptions::default()
+        };
+        let mut output = vec![];
+        let result = filter(&mut "a".as_bytes(), &mut output, "(", "b", &options);
+        assert!(result.is_err());
+        assert!(output.is_empty());
+    }
+}
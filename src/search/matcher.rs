@@ -0,0 +1,287 @@
+use std::ops::Range;
+
+use regex::{Regex, RegexBuilder};
+
+use crate::models::captures::Captures;
+
+/// A match found by a [`Matcher`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Found {
+    /// Byte range of the match in the haystack
+    pub range: Range<usize>,
+    pub captures: Captures,
+}
+
+/// An engine that finds matches of a pattern in text.
+pub trait Matcher {
+    /// Finds the first match starting at or after the byte offset `at`.
+    fn find_at(&self, haystack: &str, at: usize) -> Option<Found>;
+}
+
+/// Matches the pattern as plain text.
+#[derive(Debug, Clone)]
+pub struct LiteralMatcher {
+    needle: String,
+    ignore_case: bool,
+    whole_word: bool,
+}
+
+impl LiteralMatcher {
+    pub fn new(needle: &str, ignore_case: bool, whole_word: bool) -> Self {
+        Self {
+            needle: needle.into(),
+            ignore_case,
+            whole_word,
+        }
+    }
+
+    /// Finds the next occurrence of the needle, ignoring word boundaries.
+    fn find_candidate(&self, haystack: &str, at: usize) -> Option<Range<usize>> {
+        if !self.ignore_case {
+            let start = at + haystack[at..].find(&self.needle)?;
+            return Some(start..start + self.needle.len());
+        }
+
+        haystack[at..].char_indices().find_map(|(i, _)| {
+            let len = caseless_prefix_len(&haystack[at + i..], &self.needle)?;
+            Some(at + i..at + i + len)
+        })
+    }
+}
+
+impl Matcher for LiteralMatcher {
+    fn find_at(&self, haystack: &str, at: usize) -> Option<Found> {
+        let mut pos = at;
+        while pos <= haystack.len() {
+            let range = self.find_candidate(haystack, pos)?;
+            if !self.whole_word
+                || (is_word_boundary(haystack, range.start)
+                    && is_word_boundary(haystack, range.end))
+            {
+                return Some(Found {
+                    range,
+                    captures: Captures::default(),
+                });
+            }
+            pos = range.start + next_char_len(haystack, range.start);
+        }
+        None
+    }
+}
+
+/// Matches the pattern as a regular expression, capturing its groups.
+#[derive(Debug, Clone)]
+pub struct RegexMatcher {
+    regex: Regex,
+}
+
+impl RegexMatcher {
+    pub fn new(pattern: &str, ignore_case: bool, whole_word: bool) -> Result<Self, regex::Error> {
+        let pattern = if whole_word {
+            format!(r"\b(?:{})\b", pattern)
+        } else {
+            pattern.to_string()
+        };
+        // `^` and `$` anchor to each line, as in sed and grep
+        let regex = RegexBuilder::new(&pattern)
+            .case_insensitive(ignore_case)
+            .multi_line(true)
+            .build()?;
+        Ok(Self { regex })
+    }
+}
+
+impl Matcher for RegexMatcher {
+    fn find_at(&self, haystack: &str, at: usize) -> Option<Found> {
+        let captures = self.regex.captures_at(haystack, at)?;
+        let range = captures.get(0)?.range();
+        let groups = captures
+            .iter()
+            .map(|group| group.map(|group| group.as_str().to_string()))
+            .collect();
+        let names = self
+            .regex
+            .capture_names()
+            .map(|name| name.map(String::from))
+            .collect();
+
+        Some(Found {
+            range,
+            captures: Captures::new(groups, names),
+        })
+    }
+}
+
+/// Matches the shortest span of a line that contains the pattern's chars in
+/// order, like the fuzzy finders in editors.
+#[derive(Debug, Clone)]
+pub struct FuzzyMatcher {
+    chars: Vec<char>,
+    ignore_case: bool,
+}
+
+impl FuzzyMatcher {
+    pub fn new(pattern: &str, ignore_case: bool) -> Self {
+        Self {
+            chars: pattern.chars().collect(),
+            ignore_case,
+        }
+    }
+
+    fn chars_eq(&self, a: char, b: char) -> bool {
+        a == b || (self.ignore_case && a.to_lowercase().eq(b.to_lowercase()))
+    }
+
+    /// Finds the first match within a single line, given as byte offsets.
+    fn find_in_line(&self, line: &str) -> Option<Range<usize>> {
+        // Find where the earliest complete subsequence ends...
+        let mut matched = 0;
+        let mut end = None;
+        for (i, c) in line.char_indices() {
+            if self.chars_eq(c, self.chars[matched]) {
+                matched += 1;
+                if matched == self.chars.len() {
+                    end = Some(i + c.len_utf8());
+                    break;
+                }
+            }
+        }
+        let end = end?;
+
+        // ...then walk back from there to find the latest start, which makes
+        // it the shortest span ending at `end`
+        let mut remaining = self.chars.len();
+        for (i, c) in line[..end].char_indices().rev() {
+            if self.chars_eq(c, self.chars[remaining - 1]) {
+                remaining -= 1;
+                if remaining == 0 {
+                    return Some(i..end);
+                }
+            }
+        }
+        None
+    }
+}
+
+impl Matcher for FuzzyMatcher {
+    fn find_at(&self, haystack: &str, at: usize) -> Option<Found> {
+        if self.chars.is_empty() {
+            return None;
+        }
+
+        let mut line_start = at;
+        loop {
+            let line_end = haystack[line_start..]
+                .find('\n')
+                .map_or(haystack.len(), |pos| line_start + pos);
+            if let Some(range) = self.find_in_line(&haystack[line_start..line_end]) {
+                return Some(Found {
+                    range: line_start + range.start..line_start + range.end,
+                    captures: Captures::default(),
+                });
+            }
+            if line_end == haystack.len() {
+                return None;
+            }
+            line_start = line_end + 1;
+        }
+    }
+}
+
+/// Returns how many bytes of `haystack` match `needle` ignoring case, if
+/// `haystack` starts with it.
+fn caseless_prefix_len(haystack: &str, needle: &str) -> Option<usize> {
+    let mut len = 0;
+    let mut haystack_chars = haystack.chars();
+    for n in needle.chars() {
+        let h = haystack_chars.next()?;
+        if h != n && !h.to_lowercase().eq(n.to_lowercase()) {
+            return None;
+        }
+        len += h.len_utf8();
+    }
+    Some(len)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Same as the regex `\b`: a word char on exactly one side of `pos`.
+fn is_word_boundary(haystack: &str, pos: usize) -> bool {
+    let before = haystack[..pos]
+        .chars()
+        .next_back()
+        .is_some_and(is_word_char);
+    let after = haystack[pos..].chars().next().is_some_and(is_word_char);
+    before != after
+}
+
+fn next_char_len(haystack: &str, pos: usize) -> usize {
+    haystack[pos..].chars().next().map_or(1, char::len_utf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn find_all(matcher: &dyn Matcher, haystack: &str) -> Vec<Range<usize>> {
+        let mut ranges = vec![];
+        let mut at = 0;
+        while let Some(found) = matcher.find_at(haystack, at) {
+            at = found.range.end.max(found.range.start + 1);
+            ranges.push(found.range);
+            if at > haystack.len() {
+                break;
+            }
+        }
+        ranges
+    }
+
+    #[test]
+    fn test_literal_matcher() {
+        let matcher = LiteralMatcher::new("a.b", false, false);
+        assert_eq!(find_all(&matcher, "a.b axb a.b"), vec![0..3, 8..11]);
+    }
+
+    #[test]
+    fn test_literal_matcher_ignore_case_whole_word() {
+        let matcher = LiteralMatcher::new("straße", true, true);
+        assert_eq!(
+            find_all(&matcher, "STRAßE straßenbahn Straße"),
+            vec![0..7, 21..28]
+        );
+    }
+
+    #[test]
+    fn test_regex_matcher_captures() {
+        let matcher = RegexMatcher::new(r"(?P<first>\w+) (\w+)", false, false).unwrap();
+        let found = matcher.find_at("x: John Smith", 2).unwrap();
+        assert_eq!(found.range, 3..13);
+        assert_eq!(found.captures.get("first"), Some("John"));
+        assert_eq!(found.captures.get("2"), Some("Smith"));
+        assert_eq!(found.captures.expand("$2, $first"), "Smith, John");
+    }
+
+    #[test]
+    fn test_regex_matcher_whole_word() {
+        let matcher = RegexMatcher::new("ca.", true, true).unwrap();
+        assert_eq!(find_all(&matcher, "Cat cats CAT"), vec![0..3, 9..12]);
+    }
+
+    #[test]
+    fn test_fuzzy_matcher_finds_shortest_span() {
+        let matcher = FuzzyMatcher::new("mst", false);
+        // The first "m" isn't part of the shortest span ending at "t"
+        assert_eq!(
+            find_all(&matcher, "m match_struct\nms\nmst"),
+            vec![2..10, 18..21]
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_matcher_stays_on_one_line() {
+        let matcher = FuzzyMatcher::new("ab", true);
+        assert_eq!(find_all(&matcher, "a\nB A b"), vec![4..7]);
+    }
+}
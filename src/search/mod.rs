@@ -1,4 +1,24 @@
+pub mod contents;
+pub mod filter;
+pub mod matcher;
+pub mod options;
+pub mod query;
+pub mod refactor;
+pub mod rename;
 pub mod replace;
+pub mod ripgrep;
+pub mod rules;
+#[allow(clippy::module_inception)]
 pub mod search;
-pub use replace::replace;
-pub use search::search;
+pub mod watch;
+pub use filter::filter;
+pub use matcher::{Found, Matcher};
+pub use options::SearchOptions;
+pub use query::SearchQuery;
+pub use refactor::refactor;
+pub use rename::{find_renames, rename_paths, undo_renames, Rename, RenamePlan, RenameProblem};
+pub use replace::{find_conflicts, replace_matches, EditConflict, ReplacePlan};
+pub use ripgrep::read_rg_json;
+pub use rules::{search_rules, RuleOverlap, RuleSet};
+pub use search::{list_files, search, search_files, SearchStats};
+pub use watch::FileWatcher;
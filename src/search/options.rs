@@ -0,0 +1,84 @@
+use serde::{Deserialize, Serialize};
+
+use crate::enums::search_enums::MatchEngine;
+
+use super::matcher::{FuzzyMatcher, LiteralMatcher, Matcher, RegexMatcher};
+
+/// Options controlling how the search pattern is matched.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// The engine the pattern is matched with
+    pub engine: MatchEngine,
+    /// Match regardless of case
+    pub ignore_case: bool,
+    /// Only match whole words. Fuzzy matches ignore this.
+    pub whole_word: bool,
+}
+
+impl SearchOptions {
+    /// Builds the matcher for the pattern. Fails if the engine is regex and
+    /// the pattern isn't a valid regex.
+    pub fn build_matcher(&self, pattern: &str) -> Result<Box<dyn Matcher>, regex::Error> {
+        Ok(match self.engine {
+            MatchEngine::Literal => Box::new(LiteralMatcher::new(
+                pattern,
+                self.ignore_case,
+                self.whole_word,
+            )),
+            MatchEngine::Regex => Box::new(RegexMatcher::new(
+                pattern,
+                self.ignore_case,
+                self.whole_word,
+            )?),
+            MatchEngine::Fuzzy => Box::new(FuzzyMatcher::new(pattern, self.ignore_case)),
+        })
+    }
+
+    /// Short labels of the enabled options, for showing next to the pattern.
+    pub fn labels(&self) -> Vec<&'static str> {
+        let mut labels = vec![];
+        match self.engine {
+            MatchEngine::Literal => {}
+            MatchEngine::Regex => labels.push("regex"),
+            MatchEngine::Fuzzy => labels.push("fuzzy"),
+        }
+        if self.ignore_case {
+            labels.push("ignore case");
+        }
+        if self.whole_word {
+            labels.push("word");
+        }
+        labels
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_regex() {
+        let options = SearchOptions {
+            engine: MatchEngine::Regex,
+            ..SearchOptions::default()
+        };
+        assert!(options.build_matcher("(").is_err());
+    }
+
+    #[test]
+    fn test_literal_pattern_isnt_a_regex() {
+        let matcher = SearchOptions::default().build_matcher("(").unwrap();
+        assert!(matcher.find_at("f(x)", 0).is_some());
+    }
+
+    #[test]
+    fn test_labels() {
+        let options = SearchOptions {
+            engine: MatchEngine::Fuzzy,
+            ignore_case: true,
+            whole_word: false,
+        };
+        assert_eq!(options.labels(), vec!["fuzzy", "ignore case"]);
+    }
+}
@@ -0,0 +1,98 @@
+use super::options::SearchOptions;
+use crate::enums::search_enums::MatchEngine;
+use crate::git::GitFilter;
+
+/// What to search for and where, built up with chained setters:
+///
+/// ```
+/// use sr_rs::search::SearchQuery;
+///
+/// let query = SearchQuery::new("match_struct")
+///     .glob("src/**/*.rs")
+///     .whole_word(true);
+/// assert_eq!(query.get_pattern(), "match_struct");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchQuery {
+    pattern: String,
+    glob: String,
+    options: SearchOptions,
+    git: GitFilter,
+    skip_orig_backups: bool,
+}
+
+impl SearchQuery {
+    pub fn new<S: Into<String>>(pattern: S) -> Self {
+        Self {
+            pattern: pattern.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Sets the glob of the files to search.
+    pub fn glob<S: Into<String>>(mut self, glob: S) -> Self {
+        self.glob = glob.into();
+        self
+    }
+
+    /// Sets all the matching options at once.
+    pub fn options(mut self, options: SearchOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Sets the engine the pattern is matched with.
+    pub fn engine(mut self, engine: MatchEngine) -> Self {
+        self.options.engine = engine;
+        self
+    }
+
+    /// Treats the pattern as a regular expression instead of literal text.
+    pub fn regex(mut self, regex: bool) -> Self {
+        self.options.engine = if regex {
+            MatchEngine::Regex
+        } else {
+            MatchEngine::Literal
+        };
+        self
+    }
+
+    /// Limits the files searched to the ones passing the git filter.
+    pub fn git(mut self, git: GitFilter) -> Self {
+        self.git = git;
+        self
+    }
+
+    /// Leaves out the `.orig` backups of replaced files, which would be
+    /// replaced in again otherwise.
+    pub fn skip_orig_backups(mut self, skip: bool) -> Self {
+        self.skip_orig_backups = skip;
+        self
+    }
+
+    pub fn ignore_case(mut self, ignore_case: bool) -> Self {
+        self.options.ignore_case = ignore_case;
+        self
+    }
+
+    pub fn whole_word(mut self, whole_word: bool) -> Self {
+        self.options.whole_word = whole_word;
+        self
+    }
+
+    pub fn get_pattern(&self) -> &str {
+        &self.pattern
+    }
+    pub fn get_glob(&self) -> &str {
+        &self.glob
+    }
+    pub fn get_options(&self) -> &SearchOptions {
+        &self.options
+    }
+    pub fn get_git(&self) -> &GitFilter {
+        &self.git
+    }
+    pub fn get_skip_orig_backups(&self) -> bool {
+        self.skip_orig_backups
+    }
+}
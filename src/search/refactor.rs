@@ -0,0 +1,177 @@
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use super::rename::{rename_paths, Rename, RenamePlan};
+use super::replace::{replace_matches, ReplacePlan};
+
+/// Replaces the matches of the contents plan, then does the renames of the
+/// paths plan, so a module can be renamed along with every reference to it.
+/// Nothing is written if a rename has a problem. If a file is refused or
+/// writing the contents fails, nothing is renamed and the files already
+/// written are put back as they were. If renaming fails, the renames are
+/// rolled back and so are the contents, the originals being kept in memory
+/// until then. Returns the renames done, for
+/// [`undo_renames`](super::rename::undo_renames).
+pub fn refactor(contents: &ReplacePlan, paths: &RenamePlan) -> io::Result<Vec<Rename>> {
+    if paths.problem_count() > 0 {
+        // Reports the problems without touching anything
+        return rename_paths(paths);
+    }
+    let originals = read_originals(contents)?;
+    replace_matches(contents).map_err(|e| with_restored(e, &originals))?;
+    rename_paths(paths).map_err(|e| with_restored(e, &originals))
+}
+
+/// Puts the originals back after the error, and says so in it.
+fn with_restored(e: io::Error, originals: &[(PathBuf, Vec<u8>)]) -> io::Error {
+    match restore_originals(originals) {
+        Ok(()) => io::Error::new(
+            e.kind(),
+            format!("{}, the replaced contents were restored", e),
+        ),
+        Err(restore_error) => io::Error::new(
+            e.kind(),
+            format!(
+                "{}, and restoring the replaced contents failed: {}",
+                e, restore_error
+            ),
+        ),
+    }
+}
+
+/// The contents of the files the plan writes to.
+fn read_originals(plan: &ReplacePlan) -> io::Result<Vec<(PathBuf, Vec<u8>)>> {
+    let filepaths: HashSet<&str> = plan.matches().iter().map(|m| m.get_filepath()).collect();
+    filepaths
+        .into_iter()
+        .map(|filepath| Ok((PathBuf::from(filepath), fs::read(Path::new(filepath))?)))
+        .collect()
+}
+
+/// Writes back the files that differ from their originals, leaving the ones
+/// that were never written, such as refused files, untouched.
+fn restore_originals(originals: &[(PathBuf, Vec<u8>)]) -> io::Result<()> {
+    for (filepath, contents) in originals {
+        if fs::read(filepath).ok().as_ref() != Some(contents) {
+            fs::write(filepath, contents)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{find_renames, search, SearchQuery};
+    use std::fs;
+
+    fn plans(query: &SearchQuery, replacement: &str) -> (ReplacePlan, RenamePlan) {
+        let (matches, _) = search(query).unwrap();
+        let contents = ReplacePlan::new(matches).with_replacement(replacement);
+        let paths = RenamePlan::new(find_renames(query, replacement).unwrap());
+        (contents, paths)
+    }
+
+    #[test]
+    fn test_refactor_renames_module_and_references() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("mod.rs"), "pub mod match_struct;\n")?;
+        fs::write(dir.path().join("match_struct.rs"), "// match_struct\n")?;
+
+        let query =
+            SearchQuery::new("match_struct").glob(format!("{}/*.rs", dir.path().to_str().unwrap()));
+        let (contents, paths) = plans(&query, "matches");
+        let done = refactor(&contents, &paths)?;
+
+        assert_eq!(done.len(), 1);
+        assert_eq!(
+            fs::read_to_string(dir.path().join("mod.rs"))?,
+            "pub mod matches;\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("matches.rs"))?,
+            "// matches\n"
+        );
+        assert!(!dir.path().join("match_struct.rs").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_refactor_restores_contents_when_renaming_fails() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("mod.rs"), "pub mod match_struct;\n")?;
+        fs::write(dir.path().join("match_struct.rs"), "")?;
+
+        let query =
+            SearchQuery::new("match_struct").glob(format!("{}/*.rs", dir.path().to_str().unwrap()));
+        let (contents, paths) = plans(&query, "matches");
+        // The file disappears between planning and renaming
+        fs::remove_file(dir.path().join("match_struct.rs"))?;
+
+        let e = refactor(&contents, &paths).unwrap_err();
+        assert!(e.to_string().contains("restored"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("mod.rs"))?,
+            "pub mod match_struct;\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_refactor_restores_contents_when_a_file_is_refused() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(["-c", "user.name=sr", "-c", "user.email=sr@example.com"])
+                .args(args)
+                .status()?;
+            assert!(status.success());
+            io::Result::Ok(())
+        };
+        git(&["init", "-q"])?;
+        fs::write(dir.path().join("mod.rs"), "pub mod match_struct;\n")?;
+        fs::write(dir.path().join("match_struct.rs"), "// match_struct\n")?;
+        git(&["add", "."])?;
+        git(&["commit", "-q", "-m", "initial"])?;
+        fs::write(dir.path().join("match_struct.rs"), "// match_struct!\n")?;
+
+        let query =
+            SearchQuery::new("match_struct").glob(format!("{}/*.rs", dir.path().to_str().unwrap()));
+        let (contents, paths) = plans(&query, "matches");
+        let e = refactor(&contents.require_committed(true), &paths).unwrap_err();
+
+        assert!(e.to_string().contains("match_struct.rs"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("mod.rs"))?,
+            "pub mod match_struct;\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("match_struct.rs"))?,
+            "// match_struct!\n"
+        );
+        assert!(!dir.path().join("matches.rs").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_refactor_writes_nothing_when_a_rename_has_a_problem() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("match_struct.rs"), "match_struct\n")?;
+        fs::write(dir.path().join("matches.rs"), "")?;
+
+        let query =
+            SearchQuery::new("match_struct").glob(format!("{}/*.rs", dir.path().to_str().unwrap()));
+        let (contents, paths) = plans(&query, "matches");
+        assert!(refactor(&contents, &paths).is_err());
+        assert_eq!(
+            fs::read_to_string(dir.path().join("match_struct.rs"))?,
+            "match_struct\n"
+        );
+        Ok(())
+    }
+}
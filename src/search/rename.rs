@@ -0,0 +1,467 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+};
+
+use glob::glob;
+
+use super::query::SearchQuery;
+use super::replace::apply_matches;
+use super::search::find_matches_in_file;
+use super::watch::glob_base;
+
+/// A file or directory to move to a new path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+impl fmt::Display for Rename {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} -> {}", self.from.display(), self.to.display())
+    }
+}
+
+/// Why a rename can't be done.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RenameProblem {
+    /// Another rename of the plan moves something to the same path
+    Collision,
+    /// Something already exists at the new path
+    AlreadyExists,
+    /// The directory the new path would be in doesn't exist
+    MissingParent,
+}
+
+impl fmt::Display for RenameProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Collision => write!(f, "another path is renamed to the same name"),
+            Self::AlreadyExists => write!(f, "the new path already exists"),
+            Self::MissingParent => write!(f, "the new parent directory doesn't exist"),
+        }
+    }
+}
+
+/// A validated set of renames. Renames are only done if none has a problem.
+#[derive(Debug, Clone, Default)]
+pub struct RenamePlan {
+    renames: Vec<Rename>,
+    problems: Vec<Option<RenameProblem>>,
+}
+
+impl RenamePlan {
+    /// Builds a plan from the renames, checking each for collisions and
+    /// missing parent directories against the file system.
+    pub fn new(renames: Vec<Rename>) -> Self {
+        let mut targets: HashMap<&Path, usize> = HashMap::new();
+        for rename in &renames {
+            *targets.entry(&rename.to).or_default() += 1;
+        }
+        let sources: HashSet<&Path> = renames.iter().map(|r| r.from.as_path()).collect();
+
+        let problems = renames
+            .iter()
+            .map(|rename| {
+                if targets[rename.to.as_path()] > 1 {
+                    return Some(RenameProblem::Collision);
+                }
+                // Paths inside a renamed directory are checked where they are now
+                let current_to = original_path(&rename.to, &renames);
+                if current_to != rename.from
+                    && exists(&current_to)
+                    && !sources.contains(current_to.as_path())
+                {
+                    return Some(RenameProblem::AlreadyExists);
+                }
+                let parent = current_to.parent().unwrap_or(Path::new(""));
+                if !parent.as_os_str().is_empty() && !parent.is_dir() {
+                    return Some(RenameProblem::MissingParent);
+                }
+                None
+            })
+            .collect();
+
+        Self { renames, problems }
+    }
+
+    pub fn renames(&self) -> &[Rename] {
+        &self.renames
+    }
+
+    /// The problem of each rename, in the same order as the renames.
+    pub fn problems(&self) -> &[Option<RenameProblem>] {
+        &self.problems
+    }
+
+    pub fn problem_count(&self) -> usize {
+        self.problems.iter().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.renames.is_empty()
+    }
+}
+
+/// Applies the query's pattern to the paths of the files and directories its
+/// glob matches and its git filter keeps, returning the paths that the
+/// replacement changes. Only the part of each path below the glob's base
+/// directory is matched, so a pattern found in the name of the project
+/// directory doesn't move the whole project.
+pub fn find_renames(query: &SearchQuery, replacement: &str) -> Result<Vec<Rename>, regex::Error> {
+    if query.get_pattern().is_empty() {
+        return Ok(vec![]);
+    }
+    let matcher = query.get_options().build_matcher(query.get_pattern())?;
+
+    let paths = match query.get_git().apply(list_paths(query.get_glob())) {
+        Ok(paths) => paths,
+        Err(e) => {
+            log::error!("Failed to filter paths with git: {}", e);
+            vec![]
+        }
+    };
+    let base = glob_base(query.get_glob());
+    let mut renames = vec![];
+    for path in paths {
+        let (base, rest) = match path.strip_prefix(&base) {
+            Ok(rest) if !rest.as_os_str().is_empty() => (base.as_path(), rest),
+            // Fall back to the file name
+            _ => match (path.parent(), path.file_name()) {
+                (Some(parent), Some(name)) => (parent, Path::new(name)),
+                _ => continue,
+            },
+        };
+        let rest_str = rest.to_string_lossy().into_owned();
+        let mut matches = find_matches_in_file(&rest_str, matcher.as_ref(), &path);
+        if matches.is_empty() {
+            continue;
+        }
+        for m in matches.iter_mut() {
+            m.set_replacement_template(replacement);
+        }
+        let to = base.join(apply_matches(rest_str, matches));
+        if to != path {
+            renames.push(Rename { from: path, to });
+        }
+    }
+    Ok(renames)
+}
+
+/// Files and directories matching the glob
+fn list_paths(path_glob: &str) -> Vec<PathBuf> {
+    match glob(path_glob) {
+        Ok(paths) => paths.filter_map(Result::ok).collect(),
+        Err(e) => {
+            log::error!("Failed to read glob pattern: {}", e);
+            vec![]
+        }
+    }
+}
+
+/// Performs the renames of the plan, directories before what they contain.
+/// Either every rename is done or, if one fails, the ones already done are
+/// rolled back. Returns the renames as they were done, for [`undo_renames`].
+pub fn rename_paths(plan: &RenamePlan) -> io::Result<Vec<Rename>> {
+    if plan.problem_count() > 0 {
+        let problems: Vec<String> = plan
+            .renames()
+            .iter()
+            .zip(plan.problems())
+            .filter_map(|(rename, problem)| Some(format!("{} ({})", rename, problem.as_ref()?)))
+            .collect();
+        return Err(io::Error::other(format!(
+            "refused to rename {}",
+            problems.join(", ")
+        )));
+    }
+
+    let mut pending: Vec<&Rename> = plan.renames().iter().collect();
+    pending.sort_by_key(|rename| rename.from.components().count());
+
+    let mut done: Vec<Rename> = vec![];
+    loop {
+        // Renames inside renamed directories already happened with them
+        pending.retain(|rename| moved_path(&rename.from, &done) != rename.to);
+        if pending.is_empty() {
+            return Ok(done);
+        }
+
+        // Never overwrite anything: wait for the target to be moved away and
+        // for its directory to be renamed into place
+        let next = pending.iter().position(|rename| {
+            !exists(&rename.to)
+                && rename
+                    .to
+                    .parent()
+                    .map_or(true, |p| p.as_os_str().is_empty() || p.is_dir())
+        });
+        let Some(next) = next else {
+            rollback(&done);
+            return Err(io::Error::other(format!(
+                "can't rename {} without overwriting another path",
+                pending[0]
+            )));
+        };
+
+        let rename = pending.remove(next);
+        let from = moved_path(&rename.from, &done);
+        if let Err(e) = fs::rename(&from, &rename.to) {
+            rollback(&done);
+            return Err(io::Error::new(
+                e.kind(),
+                format!("failed to rename {}: {}", rename, e),
+            ));
+        }
+        done.push(Rename {
+            from,
+            to: rename.to.clone(),
+        });
+    }
+}
+
+fn rollback(done: &[Rename]) {
+    if let Err(e) = undo_renames(done) {
+        log::error!("Failed to roll back renames: {}", e);
+    }
+}
+
+/// Reverts renames done by [`rename_paths`], last one first.
+pub fn undo_renames(done: &[Rename]) -> io::Result<()> {
+    for rename in done.iter().rev() {
+        fs::rename(&rename.to, &rename.from)?;
+    }
+    Ok(())
+}
+
+/// Where a path ends up after the renames done so far.
+fn moved_path(path: &Path, done: &[Rename]) -> PathBuf {
+    let mut path = path.to_path_buf();
+    for rename in done {
+        if let Ok(rest) = path.strip_prefix(&rename.from) {
+            path = rename.to.join(rest);
+        }
+    }
+    path
+}
+
+/// Where a path of the renamed tree is before any rename: the inverse of
+/// [`moved_path`] for the directories of the plan.
+fn original_path(path: &Path, renames: &[Rename]) -> PathBuf {
+    let ancestor = renames
+        .iter()
+        .filter(|rename| rename.to != path)
+        .filter_map(|rename| Some((rename, path.strip_prefix(&rename.to).ok()?)))
+        .max_by_key(|(rename, _)| rename.to.components().count());
+    match ancestor {
+        Some((rename, rest)) => original_path(&rename.from, renames).join(rest),
+        None => path.to_path_buf(),
+    }
+}
+
+fn exists(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::GitFilter;
+    use std::process::Command;
+
+    fn renames_in(dir: &Path, pattern: &str, replacement: &str) -> Vec<Rename> {
+        let query = SearchQuery::new(pattern).glob(format!("{}/**/*", dir.to_str().unwrap()));
+        find_renames(&query, replacement).unwrap()
+    }
+
+    #[test]
+    fn test_find_renames() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("match_struct"))?;
+        fs::write(dir.path().join("match_struct/match_struct.rs"), "")?;
+        fs::write(dir.path().join("other.rs"), "")?;
+
+        let renames = renames_in(dir.path(), "match_struct", "matches");
+        assert_eq!(
+            renames,
+            vec![
+                Rename {
+                    from: dir.path().join("match_struct"),
+                    to: dir.path().join("matches"),
+                },
+                Rename {
+                    from: dir.path().join("match_struct/match_struct.rs"),
+                    to: dir.path().join("matches/matches.rs"),
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_renames_leaves_the_glob_base_alone() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let base = dir.path().join("match_proj/src");
+        fs::create_dir_all(&base)?;
+        fs::write(base.join("match_struct.rs"), "")?;
+
+        let query = SearchQuery::new("match").glob(format!("{}/*.rs", base.to_str().unwrap()));
+        assert_eq!(
+            find_renames(&query, "found").unwrap(),
+            vec![Rename {
+                from: base.join("match_struct.rs"),
+                to: base.join("found_struct.rs"),
+            }]
+        );
+
+        // A glob without wildcards only renames the file itself
+        let query = SearchQuery::new("match").glob(base.join("match_struct.rs").to_str().unwrap());
+        assert_eq!(
+            find_renames(&query, "found").unwrap()[0].to,
+            base.join("found_struct.rs")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_renames_applies_git_filter() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("match_struct.rs"), "")?;
+        fs::create_dir(dir.path().join("target"))?;
+        fs::write(dir.path().join("target/match_struct.o"), "")?;
+        for args in [&["init", "-q"][..], &["add", "match_struct.rs"]] {
+            let status = Command::new("git")
+                .arg("-C")
+                .arg(dir.path())
+                .args(args)
+                .status()?;
+            assert!(status.success());
+        }
+
+        let query = SearchQuery::new("match_struct")
+            .glob(format!("{}/**/*", dir.path().to_str().unwrap()))
+            .git(GitFilter {
+                tracked_only: true,
+                ..GitFilter::default()
+            });
+        assert_eq!(
+            find_renames(&query, "matches").unwrap(),
+            vec![Rename {
+                from: dir.path().join("match_struct.rs"),
+                to: dir.path().join("matches.rs"),
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_directories_before_their_contents() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::create_dir(dir.path().join("match_struct"))?;
+        fs::write(dir.path().join("match_struct/match_struct.rs"), "x")?;
+        fs::write(dir.path().join("match_struct/mod.rs"), "y")?;
+
+        let plan = RenamePlan::new(renames_in(dir.path(), "match_struct", "matches"));
+        assert_eq!(plan.problem_count(), 0);
+        let done = rename_paths(&plan)?;
+        assert_eq!(
+            fs::read_to_string(dir.path().join("matches/matches.rs"))?,
+            "x"
+        );
+        assert_eq!(fs::read_to_string(dir.path().join("matches/mod.rs"))?, "y");
+
+        undo_renames(&done)?;
+        assert_eq!(
+            fs::read_to_string(dir.path().join("match_struct/match_struct.rs"))?,
+            "x"
+        );
+        assert!(!dir.path().join("matches").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_problems() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("a1.txt"), "")?;
+        fs::write(dir.path().join("a2.txt"), "")?;
+        fs::write(dir.path().join("b.txt"), "")?;
+        fs::write(dir.path().join("c.txt"), "")?;
+        fs::write(dir.path().join("e.txt"), "")?;
+
+        let rename = |from: &str, to: &str| Rename {
+            from: dir.path().join(from),
+            to: dir.path().join(to),
+        };
+        let plan = RenamePlan::new(vec![
+            rename("a1.txt", "a.txt"),
+            rename("a2.txt", "a.txt"),
+            rename("b.txt", "c.txt"),
+            rename("c.txt", "d.txt"),
+            rename("e.txt", "missing/e.txt"),
+        ]);
+        assert_eq!(
+            plan.problems(),
+            &[
+                Some(RenameProblem::Collision),
+                Some(RenameProblem::Collision),
+                None,
+                None,
+                Some(RenameProblem::MissingParent),
+            ]
+        );
+
+        // Nothing is renamed while there are problems
+        assert!(rename_paths(&plan).is_err());
+        assert!(dir.path().join("a1.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_chain_without_overwriting() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("b.txt"), "b")?;
+        fs::write(dir.path().join("c.txt"), "c")?;
+
+        let plan = RenamePlan::new(vec![
+            Rename {
+                from: dir.path().join("b.txt"),
+                to: dir.path().join("c.txt"),
+            },
+            Rename {
+                from: dir.path().join("c.txt"),
+                to: dir.path().join("d.txt"),
+            },
+        ]);
+        rename_paths(&plan)?;
+        assert_eq!(fs::read_to_string(dir.path().join("c.txt"))?, "b");
+        assert_eq!(fs::read_to_string(dir.path().join("d.txt"))?, "c");
+        Ok(())
+    }
+
+    #[test]
+    fn test_rename_rolls_back_on_failure() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        fs::write(dir.path().join("a.txt"), "")?;
+
+        // The second rename fails, its source having disappeared
+        let plan = RenamePlan {
+            renames: vec![
+                Rename {
+                    from: dir.path().join("a.txt"),
+                    to: dir.path().join("b.txt"),
+                },
+                Rename {
+                    from: dir.path().join("gone.txt"),
+                    to: dir.path().join("c.txt"),
+                },
+            ],
+            problems: vec![None, None],
+        };
+        assert!(rename_paths(&plan).is_err());
+        assert!(dir.path().join("a.txt").exists());
+        assert!(!dir.path().join("b.txt").exists());
+        Ok(())
+    }
+}
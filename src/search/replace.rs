@@ -1,90 +1,337 @@
-use std::fs;
-use std::io::Write;
-
-use crate::app::App;
-use crate::enums::input_enums::InputBox;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use super::contents::FileContents;
+use crate::backup::Backups;
+use crate::editor::{find_unsaved_changes, reload_nvim_buffers};
+use crate::git::find_uncommitted_changes;
 use crate::models::match_struct::Match;
 
-use super::search;
+/// The set of matches to replace, each carrying its own replacement.
+#[derive(Debug, Clone, Default)]
+pub struct ReplacePlan {
+    matches: Vec<Match>,
+    conflicts: Vec<EditConflict>,
+    require_committed: bool,
+    backups: Option<Backups>,
+}
 
-pub fn replace(app: &App) -> std::io::Result<()> {
-    let search_pattern = app.input[InputBox::Search.pos()].value().to_string();
-    let replacement = app.input[InputBox::Replace.pos()].value().to_string();
-    let search_glob = app.input[InputBox::Filepath.pos()].value().to_string();
-    let res: Vec<Match> = search(search_glob, search_pattern);
-    let matches = res
-        .iter()
-        .map(|m| {
-            let mut temp = m.clone();
-            temp.set_replacement(replacement.clone());
-            temp
-        })
-        .collect::<Vec<Match>>();
-    replace_matches(&matches)
+/// Two matches editing the same bytes of a file. Only one of them can be
+/// replaced, the other one is left out of the plan.
+#[derive(Debug, Clone)]
+pub struct EditConflict {
+    pub kept: Match,
+    pub dropped: Match,
+}
+
+impl fmt::Display for EditConflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}: overlaps the match at {}:{}, skipping it",
+            self.dropped.get_filepath(),
+            self.dropped.get_line_num() + 1,
+            self.dropped.get_start_on_line() + 1,
+            self.kept.get_line_num() + 1,
+            self.kept.get_start_on_line() + 1
+        )
+    }
+}
+
+impl ReplacePlan {
+    /// Builds a plan from the matches, leaving out the ones that were
+    /// excluded or went stale. Overlapping matches are resolved as described in
+    /// [`find_conflicts`], the dropped ones are kept in [`Self::conflicts`].
+    pub fn new<I: IntoIterator<Item = Match>>(matches: I) -> Self {
+        let mut matches: Vec<Match> = matches
+            .into_iter()
+            .filter(|m| !m.is_excluded() && !m.is_stale())
+            .collect();
+        let pairs = find_conflicts(&matches);
+        let conflicts = pairs
+            .iter()
+            .map(|&(kept, dropped)| EditConflict {
+                kept: matches[kept].clone(),
+                dropped: matches[dropped].clone(),
+            })
+            .collect();
+
+        let dropped: HashSet<usize> = pairs.iter().map(|&(_, dropped)| dropped).collect();
+        let mut i = 0;
+        matches.retain(|_| {
+            i += 1;
+            !dropped.contains(&(i - 1))
+        });
+        Self {
+            matches,
+            conflicts,
+            require_committed: false,
+            backups: None,
+        }
+    }
+
+    /// Replaces every match in the plan with the same template, expanding
+    /// references to each match's capture groups.
+    pub fn with_replacement(mut self, replacement: &str) -> Self {
+        for m in self.matches.iter_mut() {
+            m.set_replacement_template(replacement);
+        }
+        self
+    }
+
+    /// Refuses to replace in files with uncommitted changes in git, so the
+    /// replacements are all that `git diff` shows afterwards.
+    pub fn require_committed(mut self, require_committed: bool) -> Self {
+        self.require_committed = require_committed;
+        self
+    }
+
+    /// Saves the original of each file before replacing in it.
+    pub fn backup(mut self, backups: Backups) -> Self {
+        self.backups = Some(backups);
+        self
+    }
+
+    pub fn matches(&self) -> &[Match] {
+        &self.matches
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.matches.is_empty()
+    }
+
+    /// Matches left out of the plan because they overlap another match.
+    pub fn conflicts(&self) -> &[EditConflict] {
+        &self.conflicts
+    }
 }
 
-// fn replace_matches(matches: &[Match]) -> std::io::Result<()> {
-//     for m in matches {
-//         let mut contents = fs::read_to_string(&m.get_filepath())?;
-//         let new_contents = contents.replace(&m.get_line(), &m.get_replacement());
-//         let mut file = fs::OpenOptions::new()
-//             .write(true)
-//             .truncate(true)
-//             .open(&m.get_filepath())?;
-//         file.write_all(new_contents.as_bytes())?;
-//     }
-//     Ok(())
-// }
-
-use std::collections::HashMap;
-
-fn replace_matches(matches: &[Match]) -> std::io::Result<()> {
+/// Finds the matches that can't be replaced because they overlap another
+/// match in the same file, ignoring excluded and stale matches. Returns pairs of
+/// `(kept, dropped)` indices into `matches`.
+///
+/// Conflicts are resolved the same way regardless of the order of the
+/// matches: the match starting first wins, then the longer one, then the one
+/// that comes first in `matches`.
+pub fn find_conflicts(matches: &[Match]) -> Vec<(usize, usize)> {
+    let mut by_file: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (i, m) in matches.iter().enumerate() {
+        if !m.is_excluded() && !m.is_stale() {
+            by_file.entry(m.get_filepath()).or_default().push(i);
+        }
+    }
+
+    let mut conflicts = vec![];
+    for (_, mut indices) in by_file {
+        indices.sort_by_key(|&i| {
+            let m = &matches[i];
+            (m.get_file_index_start(), Reverse(m.get_match_length()), i)
+        });
+
+        // The kept match reaching furthest into the file
+        let mut last: Option<(usize, usize)> = None;
+        for i in indices {
+            let start = matches[i].get_file_index_start();
+            let end = start + matches[i].get_match_length();
+            match last {
+                Some((kept, kept_end)) if start < kept_end => conflicts.push((kept, i)),
+                _ => last = Some((i, end)),
+            }
+        }
+    }
+    conflicts.sort_unstable_by_key(|&(_, dropped)| dropped);
+    conflicts
+}
+
+/// Writes the replacements of the plan to disk. Files with unsaved changes
+/// open in an editor, with uncommitted changes in git if the plan requires
+/// it, and files changed since they were searched are skipped and reported in
+/// the returned error.
+pub fn replace_matches(plan: &ReplacePlan) -> std::io::Result<()> {
     // Group the matches by file
     let mut matches_by_file: HashMap<String, Vec<Match>> = HashMap::new();
-    for m in matches {
+    for m in plan.matches() {
         matches_by_file
             .entry(m.get_filepath().to_string())
             .or_default()
             .push(m.clone());
     }
 
+    // Refuse to write files that have unsaved changes open in an editor
+    let filepaths: Vec<PathBuf> = matches_by_file.keys().map(PathBuf::from).collect();
+    let mut refused: Vec<(PathBuf, String)> = find_unsaved_changes(&filepaths)
+        .into_iter()
+        .map(|u| (u.filepath.clone(), u.to_string()))
+        .collect();
+    // Refuse to mix replacements with uncommitted changes in git
+    if plan.require_committed {
+        refused.extend(
+            find_uncommitted_changes(&filepaths)
+                .into_iter()
+                .map(|u| (u.filepath.clone(), u.to_string())),
+        );
+    }
+    for (filepath, reason) in &refused {
+        log::error!("Skipping {}", reason);
+        matches_by_file.remove(filepath.to_string_lossy().as_ref());
+    }
+
     // Process each file
+    let backup = plan.backups.as_ref().map(Backups::start);
+    let mut written = vec![];
     for (filepath, matches) in matches_by_file {
-        let mut new_contents = fs::read_to_string(&filepath)?;
-
-        // Sort the matches by their start indices in descending order
-        let mut sorted_matches = matches;
-        sorted_matches
-            .sort_unstable_by(|a, b| b.get_file_index_start().cmp(&a.get_file_index_start()));
-
-        // Replace the matches from the end of the string towards the beginning
-        for match_ in sorted_matches {
-            new_contents = new_contents[..match_.get_file_index_start()].to_string()
-                + &match_.get_replacement()
-                + &new_contents[match_.get_file_index_start() + match_.get_match_length()..];
+        let filepath = PathBuf::from(filepath);
+        let contents = FileContents::read(&filepath)?;
+        // Refuse to write files that changed since they were searched
+        if !matches_fit(&contents, &matches) {
+            let reason = format!("{} changed since it was searched", filepath.display());
+            log::error!("Skipping {}", reason);
+            refused.push((filepath, reason));
+            continue;
+        }
+        if let Some(backup) = &backup {
+            backup.save(&filepath)?;
         }
+        write_matches(&filepath, contents, matches)?;
+        written.push(filepath);
+    }
+    reload_nvim_buffers(&written);
+    if let Some(Err(e)) = plan.backups.as_ref().map(Backups::prune) {
+        log::error!("Failed to remove old backups: {}", e);
+    }
 
-        let mut file = fs::OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open(&filepath)?;
-        file.write_all(new_contents.as_bytes())?;
+    if !refused.is_empty() {
+        let refused: Vec<&str> = refused.iter().map(|(_, reason)| reason.as_str()).collect();
+        return Err(std::io::Error::other(format!(
+            "refused to replace in {}",
+            refused.join(", ")
+        )));
     }
     Ok(())
 }
 
+/// Whether each match still covers the text it matched when its file was
+/// searched, so nothing is spliced in at offsets that moved since.
+fn matches_fit(contents: &str, matches: &[Match]) -> bool {
+    matches.iter().all(|m| {
+        let start = m.get_file_index_start();
+        start
+            .checked_add(m.get_match_length())
+            .and_then(|end| contents.get(start..end))
+            == Some(m.get_text())
+    })
+}
+
+/// Rewrites a single file with its matches replaced. Memory mapped files are
+/// streamed to a temporary file next to the original, which then takes its
+/// place, so they never have to fit in memory.
+fn write_matches(filepath: &Path, contents: FileContents, matches: Vec<Match>) -> io::Result<()> {
+    let contents = match contents {
+        FileContents::Read(contents) => {
+            let new_contents = apply_matches(contents, matches);
+            let mut file = fs::OpenOptions::new()
+                .write(true)
+                .truncate(true)
+                .open(filepath)?;
+            return file.write_all(new_contents.as_bytes());
+        }
+        contents @ FileContents::Mapped(_) => contents,
+    };
+
+    let file_name = filepath.file_name().unwrap_or_default().to_string_lossy();
+    let temp_path = filepath.with_file_name(format!(".{}.sr-tmp", file_name));
+    let result = (|| {
+        let mut writer = BufWriter::new(File::create(&temp_path)?);
+        for segment in replaced_segments(&contents, &resolve_matches(matches)) {
+            writer.write_all(segment.as_bytes())?;
+        }
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        fs::set_permissions(&temp_path, fs::metadata(filepath)?.permissions())?;
+        // Unmap the original before replacing it
+        drop(contents);
+        fs::rename(&temp_path, filepath)
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
+
+/// Replaces the matches in the contents of a single file. Matches
+/// overlapping another one are skipped, see [`find_conflicts`].
+pub fn apply_matches(contents: String, matches: Vec<Match>) -> String {
+    let sorted_matches = resolve_matches(matches);
+
+    // Build the output in a single pass, copying the text between matches
+    let capacity = sorted_matches.iter().fold(contents.len(), |len, m| {
+        (len + m.get_replacement().len()).saturating_sub(m.get_match_length())
+    });
+    let mut new_contents = String::with_capacity(capacity);
+    for segment in replaced_segments(&contents, &sorted_matches) {
+        new_contents.push_str(segment);
+    }
+    new_contents
+}
+
+/// Drops the matches overlapping another one and sorts the rest by their
+/// position in the file.
+fn resolve_matches(matches: Vec<Match>) -> Vec<Match> {
+    let dropped: HashSet<usize> = find_conflicts(&matches)
+        .into_iter()
+        .map(|(_, dropped)| dropped)
+        .collect();
+    let mut sorted_matches: Vec<Match> = matches
+        .into_iter()
+        .enumerate()
+        .filter(|(i, _)| !dropped.contains(i))
+        .map(|(_, m)| m)
+        .collect();
+    sorted_matches.sort_unstable_by_key(|m| m.get_file_index_start());
+    sorted_matches
+}
+
+/// The pieces of the new contents in order: the text between matches
+/// alternating with their replacements. The matches must be sorted and not
+/// overlap.
+fn replaced_segments<'a>(
+    contents: &'a str,
+    sorted_matches: &'a [Match],
+) -> impl Iterator<Item = &'a str> {
+    let mut copied_up_to = 0;
+    let tail_start = sorted_matches
+        .last()
+        .map_or(0, |m| m.get_file_index_start() + m.get_match_length());
+
+    sorted_matches
+        .iter()
+        .flat_map(move |m| {
+            let before = &contents[copied_up_to..m.get_file_index_start()];
+            copied_up_to = m.get_file_index_start() + m.get_match_length();
+            [before, m.get_replacement()]
+        })
+        .chain(std::iter::once(&contents[tail_start..]))
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
-    use crate::app::App;
-    use crate::enums::input_enums::InputBox;
+    use crate::enums::backup_enums::BackupStyle;
+    use crate::search::{search, SearchQuery};
     use std::fs::File;
     use std::io::Write;
 
-    fn set_input_value(app: &mut App, input_box: InputBox, value: String) {
-        let input = app.input.get(input_box.pos()).unwrap();
-        app.input[input_box.pos()] = input.clone().with_value(value);
+    /// Searches the files matching the glob and replaces every match
+    fn search_and_replace(glob: &str, pattern: &str, replacement: &str) -> std::io::Result<()> {
+        let (matches, _) = search(&SearchQuery::new(pattern).glob(glob)).unwrap();
+        replace_matches(&ReplacePlan::new(matches).with_replacement(replacement))
     }
 
     #[test]
@@ -94,14 +341,8 @@ mod tests {
         let mut file = File::create(test_file_path)?;
         writeln!(file, "Hello, world! Hello, world!")?;
 
-        // Create a test App
-        let mut app = App::default();
-        set_input_value(&mut app, InputBox::Search, "world".into());
-        set_input_value(&mut app, InputBox::Replace, "Rust".into());
-        set_input_value(&mut app, InputBox::Filepath, test_file_path.into());
-
         // Call the function to test
-        replace(&app)?;
+        search_and_replace(test_file_path, "world", "Rust")?;
 
         // Check that the file content has been replaced correctly
         let content = fs::read_to_string(test_file_path)?;
@@ -125,18 +366,8 @@ mod tests {
         let mut file = File::create(&test_file_path)?;
         writeln!(file, "hehe world, hehehehehe world")?;
 
-        // Create a test App
-        let mut app = App::default();
-        set_input_value(&mut app, InputBox::Search, "hehe".into());
-        set_input_value(&mut app, InputBox::Replace, "Rust".into());
-        set_input_value(
-            &mut app,
-            InputBox::Filepath,
-            test_file_path.to_str().unwrap().into(),
-        );
-
         // Call the function to test
-        replace(&app)?;
+        search_and_replace(test_file_path.to_str().unwrap(), "hehe", "Rust")?;
 
         // Check that the file content has been replaced correctly
         let content = fs::read_to_string(&test_file_path)?;
@@ -154,8 +385,8 @@ mod tests {
         let temp_dir_path = temp_dir.path();
 
         // Create test files with various content
-        let file_names = vec!["test_file1.txt", "test_file2.txt", "test_file3.txt"];
-        let contents = vec![
+        let file_names = ["test_file1.txt", "test_file2.txt", "test_file3.txt"];
+        let contents = [
             "Hello, world!\nHello, world!\n世界Hello, 世界world!",
             "Hello, world!world!世界\nHello, 😀world!world!",
             "Hello, 世界!\nHello, 世界!",
@@ -167,20 +398,14 @@ mod tests {
             writeln!(file, "{}", content)?;
         }
 
-        // Create a test App
-        let mut app = App::default();
-        set_input_value(&mut app, InputBox::Search, "world".into());
-        set_input_value(&mut app, InputBox::Replace, "Rust".into());
-
         // Set the file path to a glob pattern that matches all files in the temp directory
         let glob_pattern = format!("{}/*", temp_dir_path.to_str().unwrap());
-        set_input_value(&mut app, InputBox::Filepath, glob_pattern);
 
         // Call the function to test
-        replace(&app)?;
+        search_and_replace(&glob_pattern, "world", "Rust")?;
 
         // Check that the file content has been replaced correctly
-        let expected_contents = vec![
+        let expected_contents = [
             "Hello, Rust!\nHello, Rust!\n世界Hello, 世界Rust!",
             "Hello, Rust!Rust!世界\nHello, 😀Rust!Rust!",
             "Hello, 世界!\nHello, 世界!",
@@ -209,18 +434,8 @@ mod tests {
         let mut file = File::create(&test_file_path)?;
         writeln!(file, "Hello, 世界!")?;
 
-        // Create a test App
-        let mut app = App::default();
-        set_input_value(&mut app, InputBox::Search, "世界".into());
-        set_input_value(&mut app, InputBox::Replace, "😀".into());
-        set_input_value(
-            &mut app,
-            InputBox::Filepath,
-            test_file_path.to_str().unwrap().into(),
-        );
-
         // Call the function to test
-        replace(&app)?;
+        search_and_replace(test_file_path.to_str().unwrap(), "世界", "😀")?;
 
         // Check that the file content has been replaced correctly
         let content = fs::read_to_string(&test_file_path)?;
@@ -230,4 +445,201 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_replace_plan_skips_excluded_matches() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_file_path = temp_dir.path().join("test_file.txt");
+        fs::write(&test_file_path, "Hello, world! Hello, world!\n")?;
+
+        let query = SearchQuery::new("world").glob(test_file_path.to_str().unwrap());
+        let (mut matches, _) = search(&query).unwrap();
+        matches[0].set_excluded(true);
+
+        let plan = ReplacePlan::new(matches).with_replacement("Rust");
+        assert_eq!(plan.matches().len(), 1);
+        replace_matches(&plan)?;
+
+        let content = fs::read_to_string(&test_file_path)?;
+        assert_eq!(content, "Hello, world! Hello, Rust!\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_refuses_files_modified_in_vim() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let modified_path = temp_dir.path().join("modified.txt");
+        let clean_path = temp_dir.path().join("clean.txt");
+        fs::write(&modified_path, "Hello, world!")?;
+        fs::write(&clean_path, "Hello, world!")?;
+
+        // A swap file with the dirty flag set, as Vim writes it
+        let mut swap = vec![0u8; 4096];
+        swap[..2].copy_from_slice(b"b0");
+        swap[1007] = 0x55;
+        fs::write(temp_dir.path().join(".modified.txt.swp"), swap)?;
+
+        let glob = format!("{}/*.txt", temp_dir.path().to_str().unwrap());
+        let err = search_and_replace(&glob, "world", "Rust").unwrap_err();
+        assert!(err.to_string().contains("modified.txt"));
+        assert_eq!(fs::read_to_string(&modified_path)?, "Hello, world!");
+        assert_eq!(fs::read_to_string(&clean_path)?, "Hello, Rust!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_refuses_uncommitted_files() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(temp_dir.path())
+                .args(["-c", "user.name=sr", "-c", "user.email=sr@example.com"])
+                .args(args)
+                .status()?;
+            assert!(status.success());
+            std::io::Result::Ok(())
+        };
+        git(&["init", "-q"])?;
+        let committed_path = temp_dir.path().join("committed.txt");
+        let dirty_path = temp_dir.path().join("dirty.txt");
+        fs::write(&committed_path, "Hello, world!")?;
+        fs::write(&dirty_path, "Hello, world!")?;
+        git(&["add", "."])?;
+        git(&["commit", "-q", "-m", "initial"])?;
+        fs::write(&dirty_path, "Hello, world! Goodbye")?;
+
+        let glob = format!("{}/*.txt", temp_dir.path().to_str().unwrap());
+        let (matches, _) = search(&SearchQuery::new("world").glob(glob)).unwrap();
+        let plan = ReplacePlan::new(matches)
+            .with_replacement("Rust")
+            .require_committed(true);
+        let err = replace_matches(&plan).unwrap_err();
+        assert!(err.to_string().contains("dirty.txt"));
+        assert_eq!(fs::read_to_string(&dirty_path)?, "Hello, world! Goodbye");
+        assert_eq!(fs::read_to_string(&committed_path)?, "Hello, Rust!");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_saves_backups() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let test_file_path = temp_dir.path().join("test_file.txt");
+        fs::write(&test_file_path, "Hello, world!")?;
+
+        let (matches, _) =
+            search(&SearchQuery::new("world").glob(test_file_path.to_str().unwrap())).unwrap();
+        let backups = Backups::new(BackupStyle::Orig);
+        replace_matches(
+            &ReplacePlan::new(matches)
+                .with_replacement("Rust")
+                .backup(backups),
+        )?;
+
+        assert_eq!(fs::read_to_string(&test_file_path)?, "Hello, Rust!");
+        assert_eq!(
+            fs::read_to_string(temp_dir.path().join("test_file.txt.orig"))?,
+            "Hello, world!"
+        );
+        Ok(())
+    }
+
+    fn match_at(filepath: &str, start: usize, length: usize, replacement: &str) -> Match {
+        let mut m = Match::new(
+            filepath.into(),
+            start,
+            length,
+            start,
+            start + length,
+            "".into(),
+            "".into(),
+            0,
+        );
+        m.set_replacement(replacement.into());
+        m
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let matches = [
+            match_at("a", 4, 2, ""),
+            match_at("a", 0, 3, ""),
+            match_at("a", 2, 4, ""),
+            match_at("b", 2, 4, ""),
+            match_at("a", 0, 5, ""),
+        ];
+        // The longest match starting first wins, whatever the order
+        assert_eq!(find_conflicts(&matches), vec![(4, 0), (4, 1), (4, 2)]);
+    }
+
+    #[test]
+    fn test_find_conflicts_ignores_excluded_and_adjacent_matches() {
+        let mut matches = [
+            match_at("a", 0, 3, ""),
+            match_at("a", 3, 3, ""),
+            match_at("a", 1, 3, ""),
+        ];
+        matches[2].set_excluded(true);
+        assert!(find_conflicts(&matches).is_empty());
+    }
+
+    #[test]
+    fn test_apply_matches_skips_overlaps() {
+        let matches = vec![
+            match_at("a", 2, 3, "X"),
+            match_at("a", 0, 4, "Y"),
+            match_at("a", 6, 1, "Z"),
+        ];
+        assert_eq!(apply_matches("abcdefgh".into(), matches), "YefZh");
+    }
+
+    #[test]
+    fn test_write_matches_streams_mapped_files() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let file_path = temp_dir.path().join("large.log");
+        fs::write(&file_path, "abcdefgh")?;
+        let path = file_path.to_str().unwrap();
+        let matches = vec![match_at(path, 2, 3, "X"), match_at(path, 6, 1, "Z")];
+
+        let mmap = unsafe { memmap2::Mmap::map(&File::open(&file_path)?)? };
+        write_matches(&file_path, FileContents::Mapped(mmap), matches)?;
+
+        assert_eq!(fs::read_to_string(&file_path)?, "abXfZh");
+        // The temporary file took the place of the original
+        assert_eq!(fs::read_dir(temp_dir.path())?.count(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_skips_files_changed_since_the_search() -> std::io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let changed = temp_dir.path().join("changed.txt");
+        let unchanged = temp_dir.path().join("unchanged.txt");
+        fs::write(&changed, "foo bar\n")?;
+        fs::write(&unchanged, "foo bar\n")?;
+        let glob = format!("{}/*.txt", temp_dir.path().display());
+        let (matches, _) = search(&SearchQuery::new("bar").glob(&glob)).unwrap();
+
+        // The match moved, and its old offset is past the end of the file
+        fs::write(&changed, "bar\n")?;
+        let err = replace_matches(&ReplacePlan::new(matches).with_replacement("baz"))
+            .unwrap_err()
+            .to_string();
+
+        assert!(err.contains("changed.txt changed since it was searched"));
+        assert_eq!(fs::read_to_string(&changed)?, "bar\n");
+        assert_eq!(fs::read_to_string(&unchanged)?, "foo baz\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_replace_plan_reports_conflicts() {
+        let plan = ReplacePlan::new([match_at("a", 0, 4, "Y"), match_at("a", 2, 3, "X")]);
+        assert_eq!(plan.matches().len(), 1);
+        assert_eq!(plan.conflicts().len(), 1);
+        assert_eq!(plan.conflicts()[0].dropped.get_replacement(), "X");
+    }
 }
@@ -0,0 +1,152 @@
+use std::io::{self, BufRead};
+
+use serde::Deserialize;
+
+use crate::models::match_struct::Match;
+
+/// Data of a `match` message from `rg --json`.
+#[derive(Debug, Deserialize)]
+struct RgMatch {
+    path: RgData,
+    lines: RgData,
+    line_number: Option<usize>,
+    absolute_offset: usize,
+    submatches: Vec<RgSubmatch>,
+}
+
+#[derive(Debug, Deserialize)]
+struct RgSubmatch {
+    /// Byte offsets into `lines`
+    start: usize,
+    end: usize,
+}
+
+/// ripgrep sends text as `{"text": ...}`, or as `{"bytes": ...}` in base64
+/// when it isn't valid UTF-8.
+#[derive(Debug, Deserialize)]
+struct RgData {
+    text: Option<String>,
+}
+
+/// Reads `rg --json` output and turns each submatch into a [`Match`]. Other
+/// message types, and matches on paths or lines that aren't UTF-8, are
+/// skipped.
+pub fn read_rg_json<R: BufRead>(reader: R) -> io::Result<Vec<Match>> {
+    let mut matches = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let message: serde_json::Value = serde_json::from_str(&line)?;
+        if message["type"] != "match" {
+            continue;
+        }
+        let data: RgMatch = serde_json::from_value(message["data"].clone())?;
+        matches.append(&mut convert_match(data));
+    }
+    Ok(matches)
+}
+
+fn convert_match(data: RgMatch) -> Vec<Match> {
+    let (Some(path), Some(lines)) = (data.path.text, data.lines.text) else {
+        log::info!("Skipping ripgrep match that isn't valid UTF-8");
+        return vec![];
+    };
+    let Some(line_number) = data.line_number else {
+        log::error!(
+            "ripgrep match in {} has no line number, run rg with -n",
+            path
+        );
+        return vec![];
+    };
+    // ripgrep counts lines from 1
+    let Some(first_line) = line_number.checked_sub(1) else {
+        log::error!("ripgrep match in {} is on line 0", path);
+        return vec![];
+    };
+
+    let mut matches = vec![];
+    for submatch in data.submatches {
+        let Some(text) = lines.get(submatch.start..submatch.end) else {
+            log::error!("ripgrep submatch out of bounds in {}", path);
+            continue;
+        };
+
+        // `lines` can hold several lines for multiline matches, use the one
+        // the submatch starts on
+        let line_start = lines[..submatch.start].rfind('\n').map_or(0, |pos| pos + 1);
+        let line_end = lines[submatch.start..]
+            .find('\n')
+            .map_or(lines.len(), |pos| submatch.start + pos);
+        let line = lines[line_start..line_end].trim_end_matches('\r');
+        let start_on_line = lines[line_start..submatch.start].chars().count();
+        let match_len_chars = text.chars().count();
+
+        matches.push(
+            Match::new(
+                path.clone(),
+                data.absolute_offset + submatch.start,
+                submatch.end - submatch.start,
+                start_on_line,
+                start_on_line + match_len_chars,
+                "".into(),
+                line.into(),
+                first_line + lines[..line_start].matches('\n').count(),
+            )
+            .with_text(text.into()),
+        );
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_rg_json() {
+        let output = r#"{"type":"begin","data":{"path":{"text":"src/test.txt"}}}
+{"type":"match","data":{"path":{"text":"src/test.txt"},"lines":{"text":"世界 world world\n"},"line_number":2,"absolute_offset":6,"submatches":[{"match":{"text":"world"},"start":7,"end":12},{"match":{"text":"world"},"start":13,"end":18}]}}
+{"type":"match","data":{"path":{"bytes":"L3RtcC//"},"lines":{"text":"world\n"},"line_number":1,"absolute_offset":0,"submatches":[{"match":{"text":"world"},"start":0,"end":5}]}}
+{"type":"end","data":{"path":{"text":"src/test.txt"},"binary_offset":null,"stats":{}}}
+"#;
+
+        let matches = read_rg_json(output.as_bytes()).unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].get_filepath(), "src/test.txt");
+        assert_eq!(matches[0].get_file_index_start(), 13);
+        assert_eq!(matches[0].get_match_length(), 5);
+        assert_eq!(matches[0].get_start_on_line(), 3);
+        assert_eq!(matches[0].get_line_num(), 1);
+        assert_eq!(matches[0].get_line(), "世界 world world");
+        assert_eq!(matches[1].get_file_index_start(), 19);
+        assert_eq!(matches[1].get_start_on_line(), 9);
+    }
+
+    #[test]
+    fn test_read_rg_json_multiline_match() {
+        let output = r#"{"type":"match","data":{"path":{"text":"a.txt"},"lines":{"text":"one\ntwo\n"},"line_number":4,"absolute_offset":10,"submatches":[{"match":{"text":"two"},"start":4,"end":7}]}}"#;
+
+        let matches = read_rg_json(output.as_bytes()).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].get_line(), "two");
+        assert_eq!(matches[0].get_line_num(), 4);
+        assert_eq!(matches[0].get_file_index_start(), 14);
+    }
+
+    #[test]
+    fn test_read_rg_json_skips_bad_submatches() {
+        let output = r#"{"type":"match","data":{"path":{"text":"a.txt"},"lines":{"text":"é world\n"},"line_number":1,"absolute_offset":0,"submatches":[{"start":5,"end":3},{"start":0,"end":1},{"start":3,"end":20},{"start":3,"end":8}]}}
+{"type":"match","data":{"path":{"text":"a.txt"},"lines":{"text":"world\n"},"line_number":0,"absolute_offset":0,"submatches":[{"start":0,"end":5}]}}"#;
+
+        let matches = read_rg_json(output.as_bytes()).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].get_text(), "world");
+    }
+
+    #[test]
+    fn test_read_rg_json_invalid() {
+        assert!(read_rg_json("not json".as_bytes()).is_err());
+    }
+}
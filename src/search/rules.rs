@@ -0,0 +1,306 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use serde::Deserialize;
+
+use super::options::SearchOptions;
+use super::search::{find_matches_in_file, list_files, read_file_contents, SearchStats};
+use crate::models::match_struct::Match;
+
+/// An ordered list of search/replace rules, read from a TOML rules file:
+///
+/// ```toml
+/// glob = "src/**/*.rs"
+///
+/// [[rule]]
+/// pattern = "match_struct"
+/// replacement = "matches"
+///
+/// [[rule]]
+/// pattern = 'get_(\w+)'
+/// replacement = "$1"
+/// engine = "regex"
+/// glob = "src/models/*.rs"
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RuleSet {
+    /// Glob of the files searched by rules without a glob of their own
+    #[serde(default)]
+    pub glob: Option<String>,
+    #[serde(default, rename = "rule")]
+    pub rules: Vec<Rule>,
+}
+
+/// A single search/replace pair of a rules file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Rule {
+    pub pattern: String,
+    /// Replacement template, `$1`/`${name}` refer to regex groups
+    #[serde(default)]
+    pub replacement: String,
+    /// Overrides the glob of the rule set
+    #[serde(default)]
+    pub glob: Option<String>,
+    #[serde(flatten)]
+    pub options: SearchOptions,
+}
+
+/// Two rules matching the same bytes. Only the match of the earlier rule is
+/// replaced, the other one is excluded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuleOverlap {
+    pub filepath: String,
+    /// Line of the excluded match
+    pub line_num: usize,
+    /// Index of the rule whose match is kept
+    pub kept_rule: usize,
+    /// Index of the rule whose match is excluded
+    pub excluded_rule: usize,
+}
+
+impl fmt::Display for RuleOverlap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: rule {} overlaps rule {}",
+            self.filepath,
+            self.line_num + 1,
+            self.excluded_rule + 1,
+            self.kept_rule + 1
+        )
+    }
+}
+
+impl RuleSet {
+    /// Parses a rule set from the contents of a rules file.
+    pub fn from_toml(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+
+    /// Reads a rules file.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        Self::from_toml(&contents).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    /// Glob of the files the rule applies to.
+    pub fn glob_of<'a>(&'a self, rule: &'a Rule) -> &'a str {
+        rule.glob
+            .as_deref()
+            .or(self.glob.as_deref())
+            .unwrap_or_default()
+    }
+}
+
+/// Runs every rule of the set, returning the matches with their replacements
+/// set, along with statistics about the run and the overlaps between rules.
+/// All rules match the original contents of the files, so a rule never sees
+/// the replacements of another one. Fails if a rule's pattern isn't a valid
+/// regex.
+pub fn search_rules(
+    rules: &RuleSet,
+) -> Result<(Vec<Match>, SearchStats, Vec<RuleOverlap>), regex::Error> {
+    let start = Instant::now();
+    let mut stats = SearchStats::default();
+    let matchers = rules
+        .rules
+        .iter()
+        .filter(|rule| !rule.pattern.is_empty())
+        .map(|rule| Ok((rule, rule.options.build_matcher(&rule.pattern)?)))
+        .collect::<Result<Vec<_>, regex::Error>>()?;
+
+    // The rules applying to each file, so every file is only read once
+    let mut files: Vec<(PathBuf, Vec<usize>)> = vec![];
+    let mut file_positions: HashMap<PathBuf, usize> = HashMap::new();
+    for (i, (rule, _)) in matchers.iter().enumerate() {
+        for path in list_files(rules.glob_of(rule)) {
+            let position = *file_positions.entry(path.clone()).or_insert_with(|| {
+                files.push((path, vec![]));
+                files.len() - 1
+            });
+            files[position].1.push(i);
+        }
+    }
+
+    let mut match_list = vec![];
+    let mut overlaps = vec![];
+    for (path, rule_indices) in &files {
+        let contents = match read_file_contents(path) {
+            Ok(contents) => contents,
+            Err(_) => {
+                stats.files_skipped += 1;
+                continue;
+            }
+        };
+        stats.files_scanned += 1;
+
+        let mut file_matches = vec![];
+        for &i in rule_indices {
+            let (rule, matcher) = &matchers[i];
+            for m in find_matches_in_file(&contents, matcher.as_ref(), path) {
+                let mut m = m.with_rule(i);
+                m.set_replacement_template(&rule.replacement);
+                file_matches.push(m);
+            }
+        }
+        overlaps.extend(exclude_overlaps(&mut file_matches));
+        if !file_matches.is_empty() {
+            stats.files_matched += 1;
+        }
+        match_list.append(&mut file_matches);
+    }
+
+    stats.elapsed = start.elapsed();
+    Ok((match_list, stats, overlaps))
+}
+
+/// Excludes the matches of a single file that overlap a match of an earlier
+/// rule. The matches must be ordered by rule.
+fn exclude_overlaps(matches: &mut [Match]) -> Vec<RuleOverlap> {
+    let mut overlaps = vec![];
+    // Byte ranges of the kept matches, keyed by start, with the rule that
+    // found them
+    let mut kept: BTreeMap<usize, (usize, usize)> = BTreeMap::new();
+
+    for m in matches.iter_mut() {
+        let start = m.get_file_index_start();
+        let end = start + m.get_match_length();
+        let before = kept.range(..end).next_back();
+        let hit = before.filter(|(_, &(kept_end, _))| kept_end > start);
+
+        match hit {
+            Some((_, &(_, kept_rule))) => {
+                m.set_excluded(true);
+                overlaps.push(RuleOverlap {
+                    filepath: m.get_filepath().to_string(),
+                    line_num: m.get_line_num(),
+                    kept_rule,
+                    excluded_rule: m.get_rule().unwrap_or_default(),
+                });
+            }
+            None => {
+                kept.insert(start, (end, m.get_rule().unwrap_or_default()));
+            }
+        }
+    }
+    overlaps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::enums::search_enums::MatchEngine;
+    use crate::search::{replace_matches, ReplacePlan};
+
+    #[test]
+    fn test_parse_rules() {
+        let rules = RuleSet::from_toml(
+            r#"
+            glob = "src/**/*.rs"
+
+            [[rule]]
+            pattern = "foo"
+            replacement = "bar"
+
+            [[rule]]
+            pattern = '(\w+)_id'
+            replacement = "${1}Id"
+            engine = "regex"
+            ignore_case = true
+            glob = "*.rs"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(rules.rules.len(), 2);
+        assert_eq!(rules.glob_of(&rules.rules[0]), "src/**/*.rs");
+        assert_eq!(rules.rules[0].options, SearchOptions::default());
+        assert_eq!(rules.glob_of(&rules.rules[1]), "*.rs");
+        assert_eq!(rules.rules[1].options.engine, MatchEngine::Regex);
+        assert!(rules.rules[1].options.ignore_case);
+    }
+
+    #[test]
+    fn test_parse_rules_unknown_engine() {
+        let err = RuleSet::from_toml("[[rule]]\npattern = \"a\"\nengine = \"glob\"\n");
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_apply_rules_in_one_pass() -> io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let file_path = temp_dir.path().join("test.txt");
+        fs::write(&file_path, "user_id owner_id\n")?;
+
+        let rules = RuleSet::from_toml(&format!(
+            r#"
+            glob = "{}"
+
+            [[rule]]
+            pattern = '(\w+)_id'
+            replacement = "${{1}}Id"
+            engine = "regex"
+
+            [[rule]]
+            pattern = "owner"
+            replacement = "author"
+            "#,
+            file_path.to_str().unwrap()
+        ))
+        .unwrap();
+
+        let (matches, stats, overlaps) = search_rules(&rules).unwrap();
+        assert_eq!(stats.files_scanned, 1);
+        assert_eq!(matches.len(), 3);
+        // "owner" lies inside "owner_id", which the first rule already replaces
+        assert_eq!(overlaps.len(), 1);
+        assert_eq!(overlaps[0].kept_rule, 0);
+        assert_eq!(overlaps[0].excluded_rule, 1);
+
+        replace_matches(&ReplacePlan::new(matches))?;
+        assert_eq!(fs::read_to_string(&file_path)?, "userId ownerId\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rules_see_original_contents() -> io::Result<()> {
+        let temp_dir = tempfile::tempdir()?;
+        let file_path = temp_dir.path().join("test.txt");
+        fs::write(&file_path, "a b\n")?;
+
+        // Swapping only works if the second rule doesn't see the first's output
+        let rules = RuleSet {
+            glob: Some(file_path.to_str().unwrap().into()),
+            rules: vec![
+                Rule {
+                    pattern: "a".into(),
+                    replacement: "b".into(),
+                    glob: None,
+                    options: SearchOptions::default(),
+                },
+                Rule {
+                    pattern: "b".into(),
+                    replacement: "a".into(),
+                    glob: None,
+                    options: SearchOptions::default(),
+                },
+            ],
+        };
+        let (matches, _, overlaps) = search_rules(&rules).unwrap();
+        assert!(overlaps.is_empty());
+        replace_matches(&ReplacePlan::new(matches))?;
+        assert_eq!(fs::read_to_string(&file_path)?, "b a\n");
+
+        Ok(())
+    }
+}
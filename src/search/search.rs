@@ -1,23 +1,72 @@
 use glob::glob;
-use std::{fs, path::PathBuf};
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
 
+use super::contents::FileContents;
+use super::matcher::Matcher;
+use super::query::SearchQuery;
+use crate::backup::is_orig_backup;
 use crate::models::match_struct::Match;
 
-pub fn search(path_g: String, search_pattern: String) -> Vec<Match> {
-    if search_pattern.is_empty() {
-        return vec![];
+/// Summary of a single search run, shown in the status bar.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchStats {
+    /// Number of files that were read and searched
+    pub files_scanned: usize,
+    /// Number of files containing at least one match
+    pub files_matched: usize,
+    /// Number of files that couldn't be searched (unreadable or binary)
+    pub files_skipped: usize,
+    /// Wall clock time the search took
+    pub elapsed: Duration,
+}
+
+/// Searches every file matching the query's glob for its pattern, returning
+/// the matches along with statistics about the run. Fails if the pattern isn't
+/// a valid regex.
+pub fn search(query: &SearchQuery) -> Result<(Vec<Match>, SearchStats), regex::Error> {
+    let files = match query.get_git().apply(list_files(query.get_glob())) {
+        Ok(files) => files,
+        Err(e) => {
+            log::error!("Failed to filter files with git: {}", e);
+            vec![]
+        }
+    };
+    search_files(query, &files)
+}
+
+/// Searches the given files for the query's pattern, ignoring its glob. Files
+/// that no longer exist are counted as skipped, `.orig` backups the query
+/// skips aren't counted at all.
+pub fn search_files(
+    query: &SearchQuery,
+    file_matches: &[PathBuf],
+) -> Result<(Vec<Match>, SearchStats), regex::Error> {
+    let start = Instant::now();
+    let mut stats = SearchStats::default();
+    if query.get_pattern().is_empty() {
+        return Ok((vec![], stats));
     }
+    let matcher = query.get_options().build_matcher(query.get_pattern())?;
 
-    let file_matches = list_files(&path_g);
     let mut match_list: Vec<Match> = vec![];
 
-    for file_match in &file_matches {
-        if let Ok(contents) = read_file_contents(file_match) {
-            match_list.append(&mut find_matches_in_file(
-                &contents,
-                &search_pattern,
-                file_match,
-            ));
+    for file_match in file_matches {
+        if query.get_skip_orig_backups() && is_orig_backup(file_match) {
+            continue;
+        }
+        match read_file_contents(file_match) {
+            Ok(contents) => {
+                stats.files_scanned += 1;
+                let mut matches = find_matches_in_file(&contents, matcher.as_ref(), file_match);
+                if !matches.is_empty() {
+                    stats.files_matched += 1;
+                }
+                match_list.append(&mut matches);
+            }
+            Err(_) => stats.files_skipped += 1,
         }
     }
 
@@ -25,23 +74,33 @@ pub fn search(path_g: String, search_pattern: String) -> Vec<Match> {
         log::info!("{:?}", match_list);
     }
 
-    match_list
+    stats.elapsed = start.elapsed();
+    Ok((match_list, stats))
 }
 
-fn read_file_contents(file_path: &PathBuf) -> Result<String, ()> {
-    match fs::read_to_string(file_path) {
-        Ok(contents) => Ok(contents),
-        Err(_) => {
-            log::error!("Couldn't read file: {:?}", file_path.to_str());
-            Err(())
-        }
-    }
+pub(crate) fn read_file_contents(file_path: &Path) -> Result<FileContents, ()> {
+    FileContents::read(file_path).map_err(|e| {
+        log::error!("Skipping {:?}: {}", file_path.to_str(), e);
+    })
 }
 
-fn find_matches_in_file(contents: &str, search_pattern: &str, file_path: &PathBuf) -> Vec<Match> {
+pub fn find_matches_in_file(contents: &str, matcher: &dyn Matcher, file_path: &Path) -> Vec<Match> {
     let mut matches = vec![];
 
-    for (i, s) in contents.match_indices(search_pattern) {
+    // Lines are counted incrementally, matches are found in order
+    let (mut counted_up_to, mut line_num) = (0, 0);
+    let mut at = 0;
+    while let Some(found) = matcher.find_at(contents, at) {
+        // Empty matches have nothing to highlight or replace, step over them
+        if found.range.is_empty() {
+            match contents[found.range.end..].chars().next() {
+                Some(c) => at = found.range.end + c.len_utf8(),
+                None => break,
+            }
+            continue;
+        }
+        at = found.range.end;
+        let (i, s) = (found.range.start, &contents[found.range.clone()]);
         let (line_start, line) = match get_line(contents, i) {
             Ok((line_start, line)) => (line_start, line),
             Err(_) => {
@@ -50,16 +109,28 @@ fn find_matches_in_file(contents: &str, search_pattern: &str, file_path: &PathBu
             }
         };
 
-        matches.push(Match::new(
-            file_path.to_string_lossy().into_owned(),
-            i,
-            s.len(),
-            i - line_start,
-            i - line_start + s.len(),
-            "".into(),
-            line.into(),
-            contents[..line_start].matches("\n").count(),
-        ));
+        line_num += contents.as_bytes()[counted_up_to..line_start]
+            .iter()
+            .filter(|&&b| b == b'\n')
+            .count();
+        counted_up_to = line_start;
+
+        // Positions on the line are counted in chars, not bytes
+        let start_on_line = contents[line_start..i].chars().count();
+        matches.push(
+            Match::new(
+                file_path.to_string_lossy().into_owned(),
+                i,
+                s.len(),
+                start_on_line,
+                start_on_line + s.chars().count(),
+                "".into(),
+                line.into(),
+                line_num,
+            )
+            .with_text(s.into())
+            .with_captures(found.captures),
+        );
     }
 
     matches
@@ -96,7 +167,9 @@ pub fn list_files(path_glob: &str) -> Vec<PathBuf> {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs::File;
+    use crate::enums::search_enums::MatchEngine;
+    use crate::search::SearchOptions;
+    use std::fs::{self, File};
     use std::io::Write;
     use tempfile::tempdir;
 
@@ -141,6 +214,39 @@ mod tests {
         assert_eq!(line, "Hello");
     }
 
+    #[test]
+    fn test_find_matches_in_file_counts_chars_on_line() {
+        let contents = "first\n世界 world";
+        let matcher = SearchOptions::default().build_matcher("world").unwrap();
+        let matches = find_matches_in_file(contents, matcher.as_ref(), Path::new("test.txt"));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].get_line_num(), 1);
+        assert_eq!(matches[0].get_start_on_line(), 3);
+        assert_eq!(matches[0].get_file_index_start(), 13);
+    }
+
+    #[test]
+    fn test_find_matches_in_file_counts_lines() {
+        let contents = "world\n\nworld world\n\n\nworld";
+        let matcher = SearchOptions::default().build_matcher("world").unwrap();
+        let matches = find_matches_in_file(contents, matcher.as_ref(), Path::new("test.txt"));
+        let line_nums: Vec<usize> = matches.iter().map(|m| m.get_line_num()).collect();
+        assert_eq!(line_nums, vec![0, 2, 2, 5]);
+    }
+
+    #[test]
+    fn test_find_matches_in_file_skips_empty_matches() {
+        let options = SearchOptions {
+            engine: MatchEngine::Regex,
+            ..SearchOptions::default()
+        };
+        let matcher = options.build_matcher("o*").unwrap();
+        let matches = find_matches_in_file("foo bar", matcher.as_ref(), Path::new("test.txt"));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].get_file_index_start(), 1);
+        assert_eq!(matches[0].get_match_length(), 2);
+    }
+
     #[test]
     fn test_read_file_contents() {
         let dir = tempdir().unwrap();
@@ -149,6 +255,31 @@ mod tests {
         writeln!(file, "Hello, world!").unwrap();
 
         let contents = read_file_contents(&file_path);
-        assert_eq!(contents.unwrap(), "Hello, world!\n");
+        assert_eq!(&*contents.unwrap(), "Hello, world!\n");
+    }
+
+    #[test]
+    fn test_read_file_contents_binary() {
+        let dir = tempdir().unwrap();
+        let file_path = dir.path().join("test.bin");
+        fs::write(&file_path, b"Hello\0world").unwrap();
+
+        assert!(read_file_contents(&file_path).is_err());
+    }
+
+    #[test]
+    fn test_search_stats() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "world world").unwrap();
+        fs::write(dir.path().join("b.txt"), "nothing here").unwrap();
+        fs::write(dir.path().join("c.txt"), b"world\0").unwrap();
+
+        let query =
+            SearchQuery::new("world").glob(format!("{}/*.txt", dir.path().to_string_lossy()));
+        let (matches, stats) = search(&query).unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(stats.files_scanned, 2);
+        assert_eq!(stats.files_matched, 1);
+        assert_eq!(stats.files_skipped, 1);
     }
 }
@@ -0,0 +1,146 @@
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Component, Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+};
+
+use glob::Pattern;
+use notify::{Event, RecommendedWatcher, RecursiveMode, Watcher};
+
+/// Watches the files matching a glob for changes on disk.
+pub struct FileWatcher {
+    glob: String,
+    pattern: Option<Pattern>,
+    /// The directory being watched, as written in the glob
+    base: PathBuf,
+    /// The directory being watched, as reported in events
+    canonical_base: PathBuf,
+    events: Receiver<notify::Result<Event>>,
+    // Events stop once the watcher is dropped
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    /// Starts watching the directory the glob searches in.
+    pub fn new(glob: &str) -> notify::Result<Self> {
+        let base = glob_base(glob);
+        let watched = if base.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            base.as_path()
+        };
+        let canonical_base = fs::canonicalize(watched)?;
+
+        let (sender, events) = channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(&canonical_base, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            glob: glob.to_string(),
+            pattern: Pattern::new(glob).ok(),
+            base,
+            canonical_base,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// The glob the watcher was started for.
+    pub fn glob(&self) -> &str {
+        &self.glob
+    }
+
+    /// Files matching the glob that changed since the last call, written the
+    /// way `list_files` lists them. Never blocks.
+    pub fn changed_paths(&self) -> Vec<PathBuf> {
+        let mut seen = HashSet::new();
+        let mut changed = vec![];
+        for event in self.events.try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    log::error!("File watcher error: {}", e);
+                    continue;
+                }
+            };
+            if event.kind.is_access() {
+                continue;
+            }
+            for path in event.paths {
+                let Some(path) = self.relative_path(&path) else {
+                    continue;
+                };
+                let matches_glob = self
+                    .pattern
+                    .as_ref()
+                    .is_some_and(|pattern| pattern.matches_path(&path));
+                if matches_glob && seen.insert(path.clone()) {
+                    changed.push(path);
+                }
+            }
+        }
+        changed
+    }
+
+    /// Turns an absolute path from an event back into a path relative to
+    /// where the glob was written from.
+    fn relative_path(&self, path: &Path) -> Option<PathBuf> {
+        let relative = path.strip_prefix(&self.canonical_base).ok()?;
+        Some(self.base.join(relative))
+    }
+}
+
+/// The directory a glob searches in: its leading components up to the first
+/// one containing a wildcard, leaving out the file name.
+pub(super) fn glob_base(glob: &str) -> PathBuf {
+    let path = Path::new(glob);
+    let components: Vec<Component> = path.components().collect();
+    let mut base = PathBuf::new();
+    for component in &components[..components.len().saturating_sub(1)] {
+        let is_wildcard = component
+            .as_os_str()
+            .to_string_lossy()
+            .contains(['*', '?', '[', '{']);
+        if is_wildcard {
+            break;
+        }
+        base.push(component);
+    }
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Duration};
+
+    #[test]
+    fn test_glob_base() {
+        assert_eq!(glob_base("src/**/*.rs"), PathBuf::from("src"));
+        assert_eq!(glob_base("src/main.rs"), PathBuf::from("src"));
+        assert_eq!(glob_base("/tmp/a*/b/*.txt"), PathBuf::from("/tmp"));
+        assert_eq!(glob_base("*.rs"), PathBuf::new());
+    }
+
+    #[test]
+    fn test_changed_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let glob = format!("{}/*.txt", dir.path().to_str().unwrap());
+        let watcher = FileWatcher::new(&glob).unwrap();
+
+        fs::write(dir.path().join("a.txt"), "Hello, world!").unwrap();
+        fs::write(dir.path().join("b.md"), "Hello, world!").unwrap();
+
+        // Events arrive asynchronously
+        let mut changed = vec![];
+        for _ in 0..50 {
+            changed.extend(watcher.changed_paths());
+            if !changed.is_empty() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(changed, vec![dir.path().join("a.txt")]);
+    }
+}
@@ -0,0 +1,101 @@
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::state_dir;
+use sr_rs::enums::search_enums::ReplaceTarget;
+use sr_rs::models::match_struct::Match;
+use sr_rs::search::SearchOptions;
+
+/// The state of the app saved between sittings. Matches aren't saved, they
+/// are searched again when the session is reopened.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Session {
+    pub search: String,
+    pub replace: String,
+    pub glob: String,
+    pub options: SearchOptions,
+    pub replace_target: ReplaceTarget,
+    /// Matches excluded in Refine mode
+    pub excluded: Vec<ExcludedMatch>,
+    pub collapsed_files: Vec<String>,
+    pub selected_row: usize,
+}
+
+/// A match excluded from the replace list, identified by its line rather
+/// than its position so that it is found again after lines were added above
+/// it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ExcludedMatch {
+    pub filepath: String,
+    pub line: String,
+    pub start_on_line: usize,
+}
+
+impl ExcludedMatch {
+    pub fn of(m: &Match) -> Self {
+        Self {
+            filepath: m.get_filepath().to_string(),
+            line: m.get_line().to_string(),
+            start_on_line: m.get_start_on_line(),
+        }
+    }
+}
+
+impl Session {
+    /// Where `s` saves the session when sr wasn't started with `--session`:
+    /// `session.json` in the state directory, outside the searched files.
+    pub fn default_path() -> Option<PathBuf> {
+        Some(state_dir()?.join("session.json"))
+    }
+
+    /// Reads a session file.
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let file = File::open(path)?;
+        serde_json::from_reader(BufReader::new(file)).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {}", path.display(), e),
+            )
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self).map_err(io::Error::other)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_load() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("sessions/refactor.json");
+        let session = Session {
+            search: "foo".into(),
+            replace: "bar".into(),
+            glob: "src/**/*.rs".into(),
+            excluded: vec![ExcludedMatch {
+                filepath: "src/main.rs".into(),
+                line: "let foo = 1;".into(),
+                start_on_line: 4,
+            }],
+            selected_row: 3,
+            ..Session::default()
+        };
+        session.save(&path)?;
+        assert_eq!(Session::load(&path)?, session);
+        Ok(())
+    }
+}
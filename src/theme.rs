@@ -0,0 +1,282 @@
+use std::{collections::HashMap, env, io};
+
+use ratatui::style::{Color, Modifier, Style};
+use serde::Deserialize;
+
+/// Names of the built-in themes.
+pub const BUILT_IN_THEMES: [&str; 4] = ["dark", "light", "high-contrast", "no-color"];
+
+/// The styles the TUI is drawn with, one per role.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Theme {
+    /// Text a replacement removes
+    pub removed: Style,
+    /// Text a replacement adds
+    pub added: Style,
+    /// Excluded matches, context lines and labels
+    pub dimmed: Style,
+    /// Notes about matches that won't be replaced and problem counts
+    pub warning: Style,
+    /// The selected input box while editing it
+    pub editing: Style,
+    /// The selected input box otherwise
+    pub selected: Style,
+    /// The selected row of the preview
+    pub highlight: Style,
+    /// Keys in the help panel
+    pub key: Style,
+    /// The help panel in Normal mode
+    pub help: Style,
+    /// File headers in the preview
+    pub file: Style,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            removed: Style::default().fg(Color::Red),
+            added: Style::default().fg(Color::Green),
+            dimmed: Style::default().fg(Color::DarkGray),
+            warning: Style::default().fg(Color::Yellow),
+            editing: Style::default().fg(Color::Yellow),
+            selected: Style::default().fg(Color::LightMagenta),
+            highlight: Style::default().add_modifier(Modifier::REVERSED),
+            key: Style::default().add_modifier(Modifier::BOLD),
+            help: Style::default().add_modifier(Modifier::RAPID_BLINK),
+            file: Style::default().add_modifier(Modifier::BOLD),
+        }
+    }
+
+    /// For terminals with a light background, where yellow and light colors
+    /// are hard to read.
+    pub fn light() -> Self {
+        Self {
+            warning: Style::default().fg(Color::Magenta),
+            editing: Style::default().fg(Color::Blue),
+            selected: Style::default().fg(Color::Magenta),
+            ..Self::dark()
+        }
+    }
+
+    /// Bold colors on plain backgrounds, without blinking.
+    pub fn high_contrast() -> Self {
+        let bold = Style::default().add_modifier(Modifier::BOLD);
+        Self {
+            removed: bold.fg(Color::Black).bg(Color::LightRed),
+            added: bold.fg(Color::Black).bg(Color::LightGreen),
+            dimmed: Style::default().fg(Color::Gray),
+            warning: bold.fg(Color::LightYellow),
+            editing: bold.fg(Color::LightYellow),
+            selected: bold.fg(Color::LightCyan),
+            highlight: bold.add_modifier(Modifier::REVERSED),
+            key: bold.add_modifier(Modifier::UNDERLINED),
+            help: Style::default(),
+            file: bold.fg(Color::White),
+        }
+    }
+
+    /// Only text attributes, for `NO_COLOR`.
+    pub fn no_color() -> Self {
+        Self {
+            removed: Style::default().add_modifier(Modifier::CROSSED_OUT),
+            added: Style::default().add_modifier(Modifier::UNDERLINED),
+            dimmed: Style::default().add_modifier(Modifier::DIM),
+            warning: Style::default().add_modifier(Modifier::BOLD),
+            editing: Style::default().add_modifier(Modifier::BOLD),
+            selected: Style::default().add_modifier(Modifier::UNDERLINED),
+            highlight: Style::default().add_modifier(Modifier::REVERSED),
+            key: Style::default().add_modifier(Modifier::BOLD),
+            help: Style::default(),
+            file: Style::default().add_modifier(Modifier::BOLD),
+        }
+    }
+
+    /// A built-in theme by name.
+    pub fn built_in(name: &str) -> Option<Self> {
+        match name {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" => Some(Self::high_contrast()),
+            "no-color" => Some(Self::no_color()),
+            _ => None,
+        }
+    }
+
+    /// The theme with the name, looked up in the user-defined themes first.
+    /// Without a name, `NO_COLOR` picks the `no-color` theme and the `dark`
+    /// one is used otherwise.
+    pub fn resolve(name: Option<&str>, themes: &HashMap<String, ThemeSpec>) -> io::Result<Self> {
+        let name = match name {
+            Some(name) => name,
+            None if env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) => "no-color",
+            None => "dark",
+        };
+        Self::resolve_named(name, themes, &mut vec![])
+    }
+
+    fn resolve_named<'a>(
+        name: &'a str,
+        themes: &'a HashMap<String, ThemeSpec>,
+        seen: &mut Vec<&'a str>,
+    ) -> io::Result<Self> {
+        let Some(spec) = themes.get(name) else {
+            return Self::built_in(name).ok_or_else(|| {
+                invalid_theme(format!(
+                    "unknown theme {:?}, the built-in themes are: {}",
+                    name,
+                    BUILT_IN_THEMES.join(", ")
+                ))
+            });
+        };
+        if seen.contains(&name) {
+            return Err(invalid_theme(format!(
+                "theme {:?} is based on itself",
+                name
+            )));
+        }
+        seen.push(name);
+        let base = match &spec.base {
+            // Customizing a built-in theme under its own name
+            Some(base) if base == name => Self::built_in(base)
+                .ok_or_else(|| invalid_theme(format!("theme {:?} is based on itself", name)))?,
+            Some(base) => Self::resolve_named(base, themes, seen)?,
+            None => Self::dark(),
+        };
+        spec.apply(base)
+    }
+}
+
+/// A user-defined theme from the config, overriding the styles of a base
+/// theme:
+///
+/// ```toml
+/// [themes.solarized]
+/// base = "light"
+/// removed = { fg = "#dc322f", modifiers = ["bold"] }
+/// added = { fg = "#859900" }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ThemeSpec {
+    /// The theme the styles are based on, `dark` by default
+    pub base: Option<String>,
+    pub removed: Option<StyleSpec>,
+    pub added: Option<StyleSpec>,
+    pub dimmed: Option<StyleSpec>,
+    pub warning: Option<StyleSpec>,
+    pub editing: Option<StyleSpec>,
+    pub selected: Option<StyleSpec>,
+    pub highlight: Option<StyleSpec>,
+    pub key: Option<StyleSpec>,
+    pub help: Option<StyleSpec>,
+    pub file: Option<StyleSpec>,
+}
+
+/// A style as written in the config. Colors are names, `#rrggbb` or
+/// indices of the terminal's palette.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StyleSpec {
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    #[serde(default)]
+    pub modifiers: Vec<String>,
+}
+
+impl ThemeSpec {
+    fn apply(&self, mut theme: Theme) -> io::Result<Theme> {
+        for (spec, style) in [
+            (&self.removed, &mut theme.removed),
+            (&self.added, &mut theme.added),
+            (&self.dimmed, &mut theme.dimmed),
+            (&self.warning, &mut theme.warning),
+            (&self.editing, &mut theme.editing),
+            (&self.selected, &mut theme.selected),
+            (&self.highlight, &mut theme.highlight),
+            (&self.key, &mut theme.key),
+            (&self.help, &mut theme.help),
+            (&self.file, &mut theme.file),
+        ] {
+            if let Some(spec) = spec {
+                *style = spec.style()?;
+            }
+        }
+        Ok(theme)
+    }
+}
+
+impl StyleSpec {
+    fn style(&self) -> io::Result<Style> {
+        let color = |color: &str| {
+            color
+                .parse::<Color>()
+                .map_err(|_| invalid_theme(format!("invalid color {:?}", color)))
+        };
+        let mut style = Style::default();
+        if let Some(fg) = &self.fg {
+            style = style.fg(color(fg)?);
+        }
+        if let Some(bg) = &self.bg {
+            style = style.bg(color(bg)?);
+        }
+        for name in &self.modifiers {
+            let modifier = Modifier::from_name(&name.to_ascii_uppercase())
+                .ok_or_else(|| invalid_theme(format!("invalid modifier {:?}", name)))?;
+            style = style.add_modifier(modifier);
+        }
+        Ok(style)
+    }
+}
+
+fn invalid_theme(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_built_in_themes() {
+        for name in BUILT_IN_THEMES {
+            assert!(Theme::resolve(Some(name), &HashMap::new()).is_ok());
+        }
+        assert!(Theme::resolve(Some("missing"), &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn test_user_defined_theme() {
+        let themes: HashMap<String, ThemeSpec> = toml::from_str(
+            r##"
+            [solarized]
+            base = "light"
+            removed = { fg = "#dc322f", modifiers = ["bold"] }
+
+            [loop]
+            base = "loop"
+
+            [broken]
+            added = { fg = "not a color" }
+            "##,
+        )
+        .unwrap();
+
+        let theme = Theme::resolve(Some("solarized"), &themes).unwrap();
+        assert_eq!(
+            theme.removed,
+            Style::default()
+                .fg(Color::Rgb(0xdc, 0x32, 0x2f))
+                .add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(theme.warning, Theme::light().warning);
+
+        assert!(Theme::resolve(Some("loop"), &themes).is_err());
+        assert!(Theme::resolve(Some("broken"), &themes).is_err());
+    }
+}
@@ -19,6 +19,6 @@ pub fn set_cursor(f: &mut Frame, app: &App, chunks: &Rc<[Rect]>, scroll: usize)
                 chunks[app.input_box_selection.pos() + 1].y + 1,
             )
         }
-        InputMode::Normal | InputMode::Refine => {}
+        InputMode::Normal | InputMode::Refine | InputMode::History => {}
     }
 }
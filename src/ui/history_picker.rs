@@ -0,0 +1,68 @@
+use ratatui::{
+    layout::{Constraint, Direction, Layout, Rect},
+    text::{Line, Span},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph},
+    Frame,
+};
+
+use crate::App;
+
+/// Draws the history picker centered over the rest of the UI.
+pub fn render_history_picker(f: &mut Frame, app: &App) {
+    let area = centered(f.size(), 80, 60);
+    let block = Block::default().borders(Borders::ALL).title("History");
+    let inner = block.inner(area);
+    f.render_widget(Clear, area);
+    f.render_widget(block, area);
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(0)])
+        .split(inner);
+    let query = app.history_picker.query.value();
+    f.render_widget(
+        Paragraph::new(Line::from(vec![
+            Span::styled("> ", app.theme.key),
+            Span::raw(query),
+        ])),
+        chunks[0],
+    );
+    f.set_cursor(
+        chunks[0].x + 2 + app.history_picker.query.visual_cursor() as u16,
+        chunks[0].y,
+    );
+
+    let label = app.theme.dimmed;
+    let items: Vec<ListItem> = app
+        .history_picker
+        .matches(&app.history)
+        .into_iter()
+        .map(|entry| {
+            ListItem::new(Line::from(vec![
+                Span::raw(entry.search.as_str()),
+                Span::styled("  ->  ", label),
+                Span::raw(entry.replace.as_str()),
+                Span::styled("  in  ", label),
+                Span::raw(entry.glob.as_str()),
+            ]))
+        })
+        .collect();
+    let mut state = ListState::default().with_selected(Some(app.history_picker.selected));
+    f.render_stateful_widget(
+        List::new(items).highlight_style(app.theme.highlight),
+        chunks[1],
+        &mut state,
+    );
+}
+
+/// A rectangle of the given percentages of the area, centered in it.
+fn centered(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let width = area.width * percent_x / 100;
+    let height = area.height * percent_y / 100;
+    Rect::new(
+        area.x + (area.width - width) / 2,
+        area.y + (area.height - height) / 2,
+        width,
+        height,
+    )
+}
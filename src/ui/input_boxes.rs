@@ -1,23 +1,21 @@
 use std::rc::Rc;
 
 use ratatui::{
-    backend::Backend,
     layout::Rect,
-    style::{Color, Modifier, Style},
+    style::Style,
     text::{Line, Span, Text},
     widgets::{Block, Borders, Paragraph},
     Frame,
 };
 use strum::IntoEnumIterator;
 
-use crate::{
-    app::App,
-    enums::input_enums::{InputBox, InputMode},
-};
+use crate::app::App;
+use crate::{InputBox, InputMode};
+use sr_rs::enums::search_enums::ReplaceTarget;
 
 use super::cursor::set_cursor;
 
-pub fn render_left_side<B: Backend>(f: &mut Frame, app: &App, left_side: &Rc<[Rect]>) {
+pub fn render_left_side(f: &mut Frame, app: &App, left_side: &Rc<[Rect]>) {
     help_message(f, app, left_side);
     let width = left_side[0].width.max(3) - 3; // keep 2 for borders and 1 for cursor
     let scroll = app.input[0].visual_scroll(width as usize); // TODO fix visual scroll later for a per input box version
@@ -25,71 +23,25 @@ pub fn render_left_side<B: Backend>(f: &mut Frame, app: &App, left_side: &Rc<[Re
     set_cursor(f, app, left_side, scroll);
 }
 
+/// Lists the keys of the current mode, as bound in the keymap.
 fn help_message(f: &mut Frame, app: &App, chunks: &Rc<[Rect]>) {
-    let (mut text, style) = match app.input_mode {
-        InputMode::Normal => (
-            Text::from(vec![
-                Line::from(vec![
-                    Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to exit"),
-                ]),
-                Line::from(vec![
-                    Span::styled("i", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to start editing"),
-                ]),
-                Line::from(vec![
-                    Span::styled("j", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to go down"),
-                ]),
-                Line::from(vec![
-                    Span::styled("k", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to go up"),
-                ]),
-            ]),
-            Style::default().add_modifier(Modifier::RAPID_BLINK),
-        ),
-        InputMode::Refine => (
-            Text::from(vec![
-                Line::from(vec![
-                    Span::styled("q", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to exit"),
-                ]),
-                Line::from(vec![
-                    Span::styled("<Tab>", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to switch back to refine search"),
-                ]),
-                Line::from(vec![
-                    Span::styled("j", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to go down"),
-                ]),
-                Line::from(vec![
-                    Span::styled("k", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to go up"),
-                ]),
-                Line::from(vec![
-                    Span::styled("d", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to remove a match"),
-                ]),
-                Line::from(vec![
-                    Span::styled("r", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to replace a match"),
-                ]),
-                Line::from(vec![
-                    Span::styled("a", Style::default().add_modifier(Modifier::BOLD)),
-                    Span::raw(" to replace all matches"),
-                ]),
-            ]),
-            Style::default(),
-        ),
-        InputMode::Editing => (
-            Text::from(vec![Line::from(vec![
-                Span::styled("<Esc>", Style::default().add_modifier(Modifier::BOLD)),
-                Span::raw(" to stop editing"),
-            ])]),
-            Style::default(),
-        ),
+    let lines: Vec<Line> = app
+        .keymap
+        .help(&app.input_mode)
+        .into_iter()
+        .map(|(keys, action)| {
+            Line::from(vec![
+                Span::styled(keys, app.theme.key),
+                Span::raw(" "),
+                Span::raw(action.description()),
+            ])
+        })
+        .collect();
+    let style = match app.input_mode {
+        InputMode::Normal => app.theme.help,
+        InputMode::Editing | InputMode::Refine | InputMode::History => Style::default(),
     };
-    text = text.patch_style(style);
+    let text = Text::from(lines).patch_style(style);
     let message = Paragraph::new(text).block(Block::default());
     f.render_widget(message, chunks[0]);
 }
@@ -101,14 +53,10 @@ fn input_boxes(f: &mut Frame, app: &App, chunks: &Rc<[Rect]>, scroll: usize) {
     }
 }
 
-fn create_input_box_widget<'a, 'b>(
-    app: &'a App,
-    input_box: &'b InputBox,
-    scroll: usize,
-) -> Paragraph<'a> {
+fn create_input_box_widget<'a>(app: &'a App, input_box: &InputBox, scroll: usize) -> Paragraph<'a> {
     let style = match app.input_mode {
-        InputMode::Editing => Style::default().fg(Color::Yellow),
-        InputMode::Normal | InputMode::Refine => Style::default().fg(Color::LightMagenta),
+        InputMode::Editing => app.theme.editing,
+        InputMode::Normal | InputMode::Refine | InputMode::History => app.theme.selected,
     };
 
     let style = if input_box == &app.input_box_selection {
@@ -117,12 +65,21 @@ fn create_input_box_widget<'a, 'b>(
         Style::default()
     };
 
-    Paragraph::new(app.input[input_box.pos().clone()].value())
+    // Show the enabled search options next to the Search box title
+    let mut labels = app.search_options.labels();
+    match app.replace_target {
+        ReplaceTarget::Contents => {}
+        ReplaceTarget::Paths => labels.push("paths"),
+        ReplaceTarget::Both => labels.push("contents + paths"),
+    }
+    let title = if input_box == &InputBox::Search && !labels.is_empty() {
+        format!("{} [{}]", input_box.title(), labels.join(", "))
+    } else {
+        input_box.title()
+    };
+
+    Paragraph::new(app.input[input_box.pos()].value())
         .style(style)
         .scroll((0, scroll as u16))
-        .block(
-            Block::default()
-                .borders(Borders::ALL)
-                .title(input_box.title().clone()),
-        )
+        .block(Block::default().borders(Borders::ALL).title(title))
 }
@@ -5,19 +5,24 @@ use ratatui::{
     Frame,
 };
 
-pub fn layout(f: &mut Frame) -> (Rc<[Rect]>, Rc<[Rect]>) {
+pub fn layout(f: &mut Frame, help_height: u16) -> (Rc<[Rect]>, Rc<[Rect]>, Rect) {
+    let main_and_status = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(1)])
+        .split(f.size());
+
     let outer = Layout::default()
         .direction(Direction::Horizontal)
         .margin(2)
         .constraints([Constraint::Percentage(30), Constraint::Percentage(70)])
-        .split(f.size());
+        .split(main_and_status[0]);
 
     let left_side = Layout::default()
         .direction(Direction::Vertical)
         .margin(2)
         .constraints(
             [
-                Constraint::Length(7),
+                Constraint::Length(help_height),
                 Constraint::Length(3),
                 Constraint::Length(3),
                 Constraint::Length(3),
@@ -30,5 +35,5 @@ pub fn layout(f: &mut Frame) -> (Rc<[Rect]>, Rc<[Rect]>) {
         .margin(2)
         .constraints([Constraint::Min(0)])
         .split(outer[1]);
-    (left_side, right_side)
+    (left_side, right_side, main_and_status[1])
 }
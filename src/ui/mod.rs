@@ -1,6 +1,9 @@
+#[allow(clippy::module_inception)]
 pub mod ui;
 pub use ui::ui;
 mod cursor;
+mod history_picker;
 mod input_boxes;
 mod layout;
 mod preview_window;
+mod status_bar;
@@ -1,33 +1,181 @@
 use std::rc::Rc;
 
-use crate::models::match_struct::Match;
+use crate::file_group::{FileGroup, PreviewRow};
+use crate::theme::Theme;
 use crate::App;
-use crate::InputBox;
-
-use crate::search::search;
+use crate::InputMode;
+use sr_rs::enums::search_enums::ReplaceTarget;
+use sr_rs::models::match_struct::Match;
+use sr_rs::search::{Rename, RenameProblem};
 
 use ratatui::{
-    backend::Backend,
-    layout::Rect,
-    text::{Line, Text},
-    widgets::{Block, Borders, Paragraph},
+    layout::{Constraint, Direction, Layout, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, List, ListItem, ListState},
     Frame,
 };
 
-pub fn render_right_side<B: Backend>(f: &mut Frame, app: &App, right_side: &Rc<[Rect]>) {
+pub fn render_right_side(f: &mut Frame, app: &App, right_side: &Rc<[Rect]>) {
     side_window(f, app, right_side);
 }
 
 fn side_window(f: &mut Frame, app: &App, chunks: &Rc<[Rect]>) {
-    let search_pattern = app.input[InputBox::Search.pos()].value().to_string();
-    let replacement = app.input[InputBox::Replace.pos()].value().to_string();
-    let search_glob = app.input[InputBox::Filepath.pos()].value().to_string();
-    let mut res: Vec<Match> = search(search_glob, search_pattern);
-    for m in res.iter_mut() {
-        m.set_replacement(replacement.clone());
-    }
-    let content: Vec<Line> = res.iter().flat_map(|line| line.tui_fmt()).collect();
+    let chunks = match app.replace_target {
+        ReplaceTarget::Contents => chunks.clone(),
+        ReplaceTarget::Paths => return rename_window(f, app, chunks[0]),
+        // Renames on top, content changes below
+        ReplaceTarget::Both => {
+            let renames_height = (app.renames.renames().len() as u16 + 2).min(chunks[0].height / 3);
+            let split = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Length(renames_height), Constraint::Min(0)])
+                .split(chunks[0]);
+            rename_window(f, app, split[0]);
+            Rc::from([split[1]])
+        }
+    };
+
+    let groups = app.file_groups();
+    let conflicting = app.conflicting_matches();
+    let items: Vec<ListItem> = app
+        .preview_rows()
+        .iter()
+        .map(|row| match row {
+            PreviewRow::File(group_index) => ListItem::new(file_header(app, &groups[*group_index])),
+            PreviewRow::Match(i) => ListItem::new(match_text(app, *i, conflicting.contains(i))),
+        })
+        .collect();
     let block = Block::default().title("Greeting").borders(Borders::ALL);
-    let paragraph = Paragraph::new(Text::from(content)).block(block);
-    f.render_widget(paragraph, chunks[0]);
+    let list = List::new(items)
+        .block(block)
+        .highlight_style(app.theme.highlight);
+
+    // Only show the selection while refining the match list
+    let selected = match app.input_mode {
+        InputMode::Refine if !app.matches.is_empty() => Some(app.selected_row),
+        InputMode::Normal | InputMode::Editing | InputMode::Refine | InputMode::History => None,
+    };
+    let mut state = ListState::default().with_selected(selected);
+    f.render_stateful_widget(list, chunks[0], &mut state);
+}
+
+/// Formats the match as a single preview line, showing the matched text
+/// alongside its replacement.
+fn match_line<'a>(m: &'a Match, theme: &Theme) -> Line<'a> {
+    let (start_byte_index, end_byte_index) = m.get_byte_indices();
+    let line = m.get_line();
+    let prefix = match m.get_rule() {
+        Some(rule) => format!("  rule {} line: {} \t", rule + 1, m.get_line_num()),
+        None => format!("  line: {} \t", m.get_line_num()),
+    };
+
+    if m.is_excluded() || m.is_stale() {
+        let spans = vec![
+            Span::raw(prefix),
+            Span::raw(&line[..start_byte_index]),
+            Span::styled(
+                &line[start_byte_index..end_byte_index],
+                Style::default().add_modifier(Modifier::CROSSED_OUT),
+            ),
+            Span::raw(&line[end_byte_index..]),
+        ];
+        return Line::from(spans).style(theme.dimmed);
+    }
+
+    let spans = vec![
+        Span::raw(prefix),
+        Span::raw(&line[..start_byte_index]),
+        Span::styled(&line[start_byte_index..end_byte_index], theme.removed),
+        Span::styled(m.get_replacement(), theme.added),
+        Span::raw(&line[end_byte_index..]),
+    ];
+
+    spans.into()
+}
+
+/// A match, annotated when it won't be replaced, between its context lines.
+fn match_text(app: &App, i: usize, conflicting: bool) -> Text<'_> {
+    let m = &app.matches[i];
+    let mut line = match_line(m, &app.theme);
+    let note = if m.is_stale() {
+        Some("  (file changed on disk, skipped)")
+    } else if conflicting {
+        Some("  (overlaps another match, skipped)")
+    } else {
+        None
+    };
+    if let Some(note) = note {
+        line.spans.push(Span::styled(note, app.theme.warning));
+    }
+
+    let (before, after) = app.context_of(m);
+    let context_line = |offset: usize, text: &str| {
+        Line::styled(format!("  line: {} \t{}", offset, text), app.theme.dimmed)
+    };
+    let first = m.get_line_num() - before.len();
+    let mut lines: Vec<Line> = before
+        .iter()
+        .enumerate()
+        .map(|(n, text)| context_line(first + n, text))
+        .collect();
+    lines.push(line);
+    lines.extend(
+        after
+            .iter()
+            .enumerate()
+            .map(|(n, text)| context_line(m.get_line_num() + 1 + n, text)),
+    );
+    Text::from(lines)
+}
+
+fn rename_window(f: &mut Frame, app: &App, area: Rect) {
+    let items: Vec<ListItem> = app
+        .renames
+        .renames()
+        .iter()
+        .zip(app.renames.problems())
+        .map(|(rename, problem)| ListItem::new(rename_line(app, rename, problem.as_ref())))
+        .collect();
+    let block = Block::default().title("Renames").borders(Borders::ALL);
+    f.render_widget(List::new(items).block(block), area);
+}
+
+fn rename_line<'a>(app: &App, rename: &'a Rename, problem: Option<&RenameProblem>) -> Line<'a> {
+    let mut spans = vec![
+        Span::styled(rename.from.to_string_lossy(), app.theme.removed),
+        Span::raw(" -> "),
+        Span::styled(rename.to.to_string_lossy(), app.theme.added),
+    ];
+    if let Some(problem) = problem {
+        spans.push(Span::styled(format!("  ({})", problem), app.theme.warning));
+    }
+    Line::from(spans)
+}
+
+fn file_header<'a>(app: &App, group: &'a FileGroup) -> Line<'a> {
+    let marker = if app.collapsed_files.contains(&group.filepath) {
+        "▶ "
+    } else {
+        "▼ "
+    };
+    let excluded = group
+        .match_indices
+        .iter()
+        .filter(|&&i| app.matches[i].is_excluded())
+        .count();
+    let count = match excluded {
+        0 => format!(" ({} matches)", group.match_indices.len()),
+        _ => format!(
+            " ({} matches, {} excluded)",
+            group.match_indices.len(),
+            excluded
+        ),
+    };
+
+    Line::from(vec![
+        Span::raw(marker),
+        Span::styled(group.filepath.as_str(), app.theme.file),
+        Span::raw(count),
+    ])
 }
@@ -0,0 +1,75 @@
+use ratatui::{
+    layout::Rect,
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+
+use crate::App;
+use sr_rs::enums::search_enums::MatchSource;
+
+pub fn render_status_bar(f: &mut Frame, app: &App, area: Rect) {
+    let stats = &app.search_stats;
+    let label = app.theme.dimmed;
+    let mut spans = vec![
+        Span::styled(" matches: ", label),
+        Span::raw(app.matches.len().to_string()),
+        Span::styled("  files matched: ", label),
+        Span::raw(stats.files_matched.to_string()),
+        Span::styled("  scanned: ", label),
+        Span::raw(stats.files_scanned.to_string()),
+        Span::styled("  skipped: ", label),
+        Span::raw(stats.files_skipped.to_string()),
+        Span::styled("  excluded: ", label),
+        Span::raw(app.excluded_count().to_string()),
+        Span::styled("  conflicts: ", label),
+        conflicts_span(app, app.conflicting_matches().len()),
+        Span::styled("  time: ", label),
+        Span::raw(format!("{:.2?}", stats.elapsed)),
+    ];
+    match app.match_source {
+        MatchSource::Search => {}
+        MatchSource::Ripgrep => {
+            spans.push(Span::styled("  source: ", label));
+            spans.push(Span::raw("rg --json"));
+        }
+        MatchSource::Rules => {
+            spans.push(Span::styled("  source: ", label));
+            spans.push(Span::raw(format!("{} rules", app.rules.rules.len())));
+        }
+    }
+    if app.replace_target.includes_paths() {
+        spans.push(Span::styled("  renames: ", label));
+        spans.push(Span::raw(app.renames.renames().len().to_string()));
+        spans.push(Span::styled("  problems: ", label));
+        spans.push(conflicts_span(app, app.renames.problem_count()));
+    }
+    if app.git.tracked_only {
+        spans.push(Span::styled("  git: ", label));
+        spans.push(Span::raw("tracked"));
+    }
+    if let Some(reference) = &app.git.changed_since {
+        spans.push(Span::styled("  changed since: ", label));
+        spans.push(Span::raw(reference.as_str()));
+    }
+    if app.require_committed {
+        spans.push(Span::styled("  committed only", label));
+    }
+    if app.watcher.is_some() {
+        spans.push(Span::styled("  watching", label));
+    }
+    if let Some(message) = &app.status_message {
+        spans.push(Span::styled("  | ", label));
+        spans.push(Span::styled(message, app.theme.warning));
+    }
+    f.render_widget(Paragraph::new(Line::from(spans)), area);
+}
+
+/// Highlights a number of problems, such as overlapping matches that won't be
+/// replaced.
+fn conflicts_span(app: &App, conflicts: usize) -> Span<'static> {
+    match conflicts {
+        0 => Span::raw("0"),
+        _ => Span::styled(conflicts.to_string(), app.theme.warning),
+    }
+}
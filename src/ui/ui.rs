@@ -1,13 +1,20 @@
 use crate::App;
+use crate::InputMode;
 
-use ratatui::{backend::Backend, Frame};
+use ratatui::Frame;
 
+use super::history_picker::render_history_picker;
 use super::input_boxes::render_left_side;
 use super::layout::layout;
 use super::preview_window::render_right_side;
+use super::status_bar::render_status_bar;
 
-pub fn ui<B: Backend>(f: &mut Frame, app: &App) {
-    let (left_side, right_side) = layout(f);
-    render_left_side::<B>(f, app, &left_side);
-    render_right_side::<B>(f, app, &right_side);
+pub fn ui(f: &mut Frame, app: &App) {
+    let (left_side, right_side, status_bar) = layout(f, app.keymap.help_height() as u16);
+    render_left_side(f, app, &left_side);
+    render_right_side(f, app, &right_side);
+    render_status_bar(f, app, status_bar);
+    if let InputMode::History = app.input_mode {
+        render_history_picker(f, app);
+    }
 }